@@ -5,6 +5,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 "protos/spl_token.proto",
                 "protos/daos_fund.proto",
                 "protos/system.proto",
+                "protos/geyser.proto",
+                "protos/commitment.proto",
+                "protos/failed_instruction.proto",
+                "protos/blockhash.proto",
+                "protos/slot_timing.proto",
+                "protos/leader_schedule.proto",
+                "protos/priority_fee.proto",
             ],
             &["protos"],
         )?;
@@ -1,2 +1,25 @@
 pub const TRACKED_TOKEN_ADDRESSES: &str = "tracked_spl_token_addresses";
-pub const TRACKED_USER_ADDRESSES: &str = "tracked_user_addresses";
\ No newline at end of file
+pub const TRACKED_USER_ADDRESSES: &str = "tracked_user_addresses";
+
+/// Control channel producers may publish to after changing the tracked sets, for deployments
+/// where keyspace notifications are disabled.
+pub const TRACKED_ADDRESSES_CHANGED_CHANNEL: &str = "tracked_addresses_changed";
+
+/// Hash of the latency and error statistics of every blockhash RPC endpoint, keyed by endpoint.
+pub const BLOCKHASH_ENDPOINT_HEALTH: &str = "blockhash_endpoint_health";
+
+/// Hash of the latest `SlotTimingUpdate` statistics, keyed by field name.
+pub const SLOT_TIMING: &str = "slot_timing";
+
+/// JSON of the current and upcoming leaders, expiring when it is no longer refreshed.
+pub const UPCOMING_LEADERS: &str = "upcoming_leaders";
+
+/// Hash of the global priority fee recommendations, `priority_fee_estimate:{account}` holds those
+/// of the busiest writable accounts.
+pub const PRIORITY_FEE_ESTIMATE: &str = "priority_fee_estimate";
+
+/// Hash of the state of the blockhash processor (`running` or `degraded`) and its last error.
+pub const BLOCKHASH_PROCESSOR_STATE: &str = "blockhash_processor_state";
+
+/// JSON of the recent blockhashes and slot timing, restored when the blockhash processor restarts.
+pub const BLOCKHASH_WARM_STATE: &str = "blockhash_warm_state";
\ No newline at end of file
@@ -5,4 +5,16 @@ pub const PUMP_FUN_BONDING_CURVE_UPDATE: &str = "pump_fun_bonding_curve_update";
 pub const LAMPORTS_BALANCE_UPDATE: &str = "lamports_balance_update";
 pub const SPL_TOKEN_BALANCE_UPDATE: &str = "spl_token_balance_update";
 
-pub const SERUM_INITIALIZE_MARKET_UPDATE: &str = "serum_initialize_market_update";
\ No newline at end of file
+pub const SERUM_INITIALIZE_MARKET_UPDATE: &str = "serum_initialize_market_update";
+
+pub const TRANSACTION_CONFIRMED_UPDATE: &str = "transaction_confirmed_update";
+pub const TRANSACTION_FINALIZED_UPDATE: &str = "transaction_finalized_update";
+pub const TRANSACTION_DROPPED_UPDATE: &str = "transaction_dropped_update";
+
+pub const FAILED_INSTRUCTION: &str = "failed_instruction";
+
+pub const BLOCKHASH_STALENESS_ALARM: &str = "blockhash_staleness_alarm";
+
+pub const SLOT_TIMING_UPDATE: &str = "slot_timing_update";
+
+pub const UPCOMING_LEADERS_UPDATE: &str = "upcoming_leaders_update";
\ No newline at end of file
@@ -0,0 +1,113 @@
+use solana_sdk::{
+    address_lookup_table::state::AddressLookupTable,
+    message::v0::{LoadedAddresses, MessageAddressTableLookup},
+    pubkey::Pubkey
+};
+use base64::{prelude::BASE64_STANDARD, Engine};
+use std::{collections::HashMap, sync::{Arc, Mutex}};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::info;
+
+use super::json_rpc::{JsonRpcClient, JsonRpcError};
+
+/// Maximum number of accounts accepted by a single `getMultipleAccounts` request.
+const ACCOUNTS_BATCH_SIZE: usize = 100;
+
+#[derive(Debug, Deserialize)]
+struct AccountInfo {
+    /// `[data, encoding]`
+    data: (String, String),
+}
+
+#[derive(Debug, Deserialize)]
+struct MultipleAccountsResult {
+    value: Vec<Option<AccountInfo>>,
+}
+
+/// Resolves the addresses loaded through address lookup tables by v0 transactions, for the
+/// providers that do not include `loadedAddresses` in the transaction meta. Lookup tables are
+/// append-only, so a cached table is only re-fetched once a transaction references an index
+/// past its known length.
+pub struct AddressLookupTableCache {
+    rpc_client: JsonRpcClient,
+    tables: Mutex<HashMap<Pubkey, Arc<Vec<Pubkey>>>>,
+}
+
+impl AddressLookupTableCache {
+    pub fn new(rpc_url: &str) -> Self {
+        Self {
+            rpc_client: JsonRpcClient::new(rpc_url),
+            tables: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the loaded addresses in the order the runtime appends them to the static account
+    /// keys: the writable addresses of every lookup first, followed by the readonly ones.
+    pub async fn resolve(&self, lookups: &[MessageAddressTableLookup]) -> Result<LoadedAddresses, JsonRpcError> {
+        let missing: Vec<Pubkey> = {
+            let tables = self.tables.lock().unwrap();
+            lookups
+                .iter()
+                .filter(|lookup| {
+                    let required = lookup.writable_indexes.iter()
+                        .chain(lookup.readonly_indexes.iter())
+                        .map(|index| *index as usize + 1)
+                        .max()
+                        .unwrap_or(0);
+                    tables.get(&lookup.account_key).map_or(true, |addresses| addresses.len() < required)
+                })
+                .map(|lookup| lookup.account_key)
+                .collect()
+        };
+
+        for batch in missing.chunks(ACCOUNTS_BATCH_SIZE) {
+            self.fetch_tables(batch).await?;
+        }
+
+        let tables = self.tables.lock().unwrap();
+        let mut loaded_addresses = LoadedAddresses::default();
+        for lookup in lookups {
+            let addresses = tables.get(&lookup.account_key)
+                .ok_or_else(|| format!("Address lookup table {} not found", lookup.account_key))?;
+
+            let select = |indexes: &[u8]| -> Result<Vec<Pubkey>, JsonRpcError> {
+                indexes
+                    .iter()
+                    .map(|index| addresses.get(*index as usize).copied().ok_or_else(|| {
+                        format!("Index {} out of range for address lookup table {}", index, lookup.account_key).into()
+                    }))
+                    .collect()
+            };
+
+            loaded_addresses.writable.extend(select(&lookup.writable_indexes)?);
+            loaded_addresses.readonly.extend(select(&lookup.readonly_indexes)?);
+        }
+
+        Ok(loaded_addresses)
+    }
+
+    async fn fetch_tables(&self, keys: &[Pubkey]) -> Result<(), JsonRpcError> {
+        let addresses: Vec<String> = keys.iter().map(ToString::to_string).collect();
+        let accounts: MultipleAccountsResult = self.rpc_client
+            .call("getMultipleAccounts", json!([addresses, { "encoding": "base64" }]))
+            .await?;
+
+        let mut tables = self.tables.lock().unwrap();
+        for (key, account) in keys.iter().zip(accounts.value) {
+            let account = match account {
+                Some(account) => account,
+                None => continue,
+            };
+
+            let data = BASE64_STANDARD.decode(&account.data.0)?;
+            let table = AddressLookupTable::deserialize(&data)
+                .map_err(|e| format!("Invalid address lookup table {}: {}", key, e))?;
+
+            info!("Cached address lookup table {} ({} addresses)", key, table.addresses.len());
+            tables.insert(*key, Arc::new(table.addresses.to_vec()));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+use std::time::{Duration, Instant};
+use serde::Serialize;
+
+use super::rolling_average::RollingAverage;
+
+/// Consecutive failures after which an endpoint is ejected from the rotation.
+const EJECT_AFTER_FAILURES: u32 = 3;
+
+/// Cool-down of the first ejection, doubled for every consecutive ejection.
+const BASE_COOL_DOWN: Duration = Duration::from_secs(10);
+const MAX_COOL_DOWN: Duration = Duration::from_secs(300);
+
+/// Latency assumed for endpoints that have not answered any request yet, a typical round trip
+/// to a nearby RPC node.
+const UNTESTED_LATENCY: Duration = Duration::from_millis(250);
+
+/// Latency and error statistics of a single endpoint, acting as a circuit breaker: an endpoint
+/// failing repeatedly is ejected for a cool-down period, after which it is given another try.
+#[derive(Debug, Clone, Default)]
+pub struct EndpointHealth {
+    latency: RollingAverage<50>,
+    successes: u64,
+    failures: u64,
+    consecutive_failures: u32,
+    ejections: u32,
+    ejected_until: Option<Instant>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointHealthSummary {
+    pub endpoint: String,
+    pub average_latency_ms: f64,
+    pub successes: u64,
+    pub failures: u64,
+    pub error_rate: f64,
+    pub ejected: bool,
+}
+
+impl EndpointHealth {
+    pub fn record_success(&mut self, latency: Duration) {
+        self.latency.push(latency.as_secs_f64());
+        self.successes += 1;
+        self.consecutive_failures = 0;
+        self.ejections = 0;
+        self.ejected_until = None;
+    }
+
+    /// Returns true if this failure ejected the endpoint.
+    pub fn record_failure(&mut self) -> bool {
+        self.failures += 1;
+        self.consecutive_failures += 1;
+
+        if self.consecutive_failures < EJECT_AFTER_FAILURES {
+            return false;
+        }
+
+        let cool_down = BASE_COOL_DOWN
+            .saturating_mul(2u32.saturating_pow(self.ejections))
+            .min(MAX_COOL_DOWN);
+        self.ejections += 1;
+        self.consecutive_failures = 0;
+        self.ejected_until = Some(Instant::now() + cool_down);
+        true
+    }
+
+    pub fn is_available(&self, now: Instant) -> bool {
+        self.ejected_until.map_or(true, |until| now >= until)
+    }
+
+    pub fn ejected_until(&self) -> Option<Instant> {
+        self.ejected_until
+    }
+
+    /// Lower is better: average latency, penalized by the share of failed requests. Endpoints
+    /// without any sample yet score as if they had `UNTESTED_LATENCY`, endpoints that only ever
+    /// failed score worst.
+    pub fn score(&self) -> f64 {
+        match (self.successes, self.failures) {
+            (0, 0) => UNTESTED_LATENCY.as_secs_f64(),
+            (0, _) => f64::INFINITY,
+            _ => self.latency.average() * (1.0 + 10.0 * self.error_rate()),
+        }
+    }
+
+    pub fn error_rate(&self) -> f64 {
+        let total = self.successes + self.failures;
+        if total == 0 {
+            0.0
+        } else {
+            self.failures as f64 / total as f64
+        }
+    }
+
+    pub fn summary(&self, endpoint: &str) -> EndpointHealthSummary {
+        EndpointHealthSummary {
+            endpoint: endpoint.to_string(),
+            average_latency_ms: self.latency.average() * 1000.0,
+            successes: self.successes,
+            failures: self.failures,
+            error_rate: self.error_rate(),
+            ejected: !self.is_available(Instant::now()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fails the endpoint until it is ejected, returning the cool-down it was ejected for.
+    fn eject(health: &mut EndpointHealth) -> Duration {
+        for _ in 1..EJECT_AFTER_FAILURES {
+            assert!(!health.record_failure());
+        }
+
+        let before = Instant::now();
+        assert!(health.record_failure());
+        health.ejected_until().unwrap() - before
+    }
+
+    fn assert_close(cool_down: Duration, expected: Duration) {
+        assert!(cool_down >= expected && cool_down - expected < Duration::from_millis(100), "{:?} != {:?}", cool_down, expected);
+    }
+
+    #[test]
+    fn ejects_after_consecutive_failures_and_doubles_the_cool_down() {
+        let mut health = EndpointHealth::default();
+
+        assert_close(eject(&mut health), BASE_COOL_DOWN);
+        assert!(!health.is_available(Instant::now()));
+        assert!(health.is_available(health.ejected_until().unwrap()));
+
+        assert_close(eject(&mut health), BASE_COOL_DOWN * 2);
+        assert_close(eject(&mut health), BASE_COOL_DOWN * 4);
+
+        for _ in 0..10 {
+            eject(&mut health);
+        }
+        assert_close(eject(&mut health), MAX_COOL_DOWN);
+    }
+
+    #[test]
+    fn success_resets_the_cool_down() {
+        let mut health = EndpointHealth::default();
+        eject(&mut health);
+        eject(&mut health);
+
+        health.record_success(Duration::from_millis(100));
+        assert!(health.is_available(Instant::now()));
+        assert_close(eject(&mut health), BASE_COOL_DOWN);
+    }
+
+    #[test]
+    fn a_success_in_between_failures_prevents_the_ejection() {
+        let mut health = EndpointHealth::default();
+        for _ in 0..10 {
+            assert!(!health.record_failure());
+            health.record_success(Duration::from_millis(100));
+        }
+        assert!(health.is_available(Instant::now()));
+    }
+
+    #[test]
+    fn scores_untested_endpoints_neutrally_and_failing_only_endpoints_worst() {
+        let untested = EndpointHealth::default();
+        assert_eq!(untested.score(), UNTESTED_LATENCY.as_secs_f64());
+        assert_eq!(untested.error_rate(), 0.0);
+
+        let mut failing = EndpointHealth::default();
+        failing.record_failure();
+        assert_eq!(failing.score(), f64::INFINITY);
+
+        let mut fast = EndpointHealth::default();
+        fast.record_success(Duration::from_millis(50));
+        let mut slow = EndpointHealth::default();
+        slow.record_success(Duration::from_millis(500));
+        assert!(fast.score() < untested.score());
+        assert!(untested.score() < slow.score());
+
+        // A failure out of two requests multiplies the latency by 1 + 10 * 0.5.
+        let mut flaky = EndpointHealth::default();
+        flaky.record_success(Duration::from_millis(50));
+        flaky.record_failure();
+        assert_eq!(flaky.error_rate(), 0.5);
+        assert!((flaky.score() - 0.05 * 6.0).abs() < 1e-9);
+    }
+}
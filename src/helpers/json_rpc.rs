@@ -0,0 +1,57 @@
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+
+pub type JsonRpcError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug, Deserialize)]
+struct JsonRpcErrorObject {
+    code: i64,
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonRpcResponse {
+    #[serde(default)]
+    result: Value,
+    error: Option<JsonRpcErrorObject>,
+}
+
+/// Minimal Solana JSON-RPC over HTTP client. We only need a handful of methods, which does
+/// not justify pulling in the full `solana-client` dependency tree.
+#[derive(Clone)]
+pub struct JsonRpcClient {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl JsonRpcClient {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub async fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T, JsonRpcError> {
+        let request_body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+
+        let response: JsonRpcResponse = self.client
+            .post(&self.url)
+            .json(&request_body)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        if let Some(error) = response.error {
+            return Err(format!("{} failed with RPC error {}: {}", method, error.code, error.message).into());
+        }
+
+        Ok(serde_json::from_value(response.result)?)
+    }
+}
@@ -1 +1,7 @@
-pub mod rolling_average;
\ No newline at end of file
+pub mod rolling_average;
+pub mod json_rpc;
+pub mod signature_cache;
+pub mod address_lookup_tables;
+pub mod slot_feed;
+pub mod endpoint_health;
+pub mod rolling_stats;
\ No newline at end of file
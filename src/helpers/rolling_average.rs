@@ -6,6 +6,12 @@ pub struct RollingAverage<const CAP: usize> {
     count: usize,
 }
 
+impl<const CAP: usize> Default for RollingAverage<CAP> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<const CAP: usize> RollingAverage<CAP> {
     pub fn new () -> Self {
         Self {
@@ -30,6 +36,15 @@ impl<const CAP: usize> RollingAverage<CAP> {
         self.idx = (self.idx + 1) % CAP;
     }
 
+    /// The samples of the window, oldest first.
+    pub fn values(&self) -> Vec<f64> {
+        if self.count < CAP {
+            self.data[..self.count].to_vec()
+        } else {
+            [&self.data[self.idx..], &self.data[..self.idx]].concat()
+        }
+    }
+
     pub fn average(&self) -> f64 {
         if self.count == 0 {
             0.0
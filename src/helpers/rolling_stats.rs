@@ -0,0 +1,194 @@
+/// Rolling window of samples supporting percentiles, unlike `RollingAverage` which only keeps
+/// track of the mean.
+#[derive(Debug, Clone)]
+pub struct RollingStats<const CAP: usize> {
+    data: [f64; CAP],
+    sum: f64,
+    idx: usize,
+    count: usize,
+}
+
+impl<const CAP: usize> Default for RollingStats<CAP> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const CAP: usize> RollingStats<CAP> {
+    pub fn new() -> Self {
+        Self {
+            data: [0.0; CAP],
+            sum: 0.0,
+            idx: 0,
+            count: 0,
+        }
+    }
+
+    pub fn push(&mut self, val: f64) {
+        if self.count == CAP {
+            self.sum -= self.data[self.idx];
+        } else {
+            self.count += 1;
+        }
+
+        self.data[self.idx] = val;
+        self.sum += val;
+
+        self.idx = (self.idx + 1) % CAP;
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn sum(&self) -> f64 {
+        self.sum
+    }
+
+    /// The samples of the window, oldest first.
+    pub fn values(&self) -> Vec<f64> {
+        if self.count < CAP {
+            self.samples().to_vec()
+        } else {
+            [&self.data[self.idx..], &self.data[..self.idx]].concat()
+        }
+    }
+
+    pub fn average(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.sum / self.count as f64
+        }
+    }
+
+    pub fn min(&self) -> f64 {
+        self.samples().iter().copied().reduce(f64::min).unwrap_or(0.0)
+    }
+
+    pub fn max(&self) -> f64 {
+        self.samples().iter().copied().reduce(f64::max).unwrap_or(0.0)
+    }
+
+    /// Nearest-rank percentile of the window, `p` ranging from 0 to 100.
+    pub fn percentile(&self, p: f64) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+
+        let mut sorted = self.samples().to_vec();
+        sorted.sort_by(f64::total_cmp);
+
+        let rank = ((p / 100.0) * self.count as f64).ceil() as usize;
+        sorted[rank.clamp(1, self.count) - 1]
+    }
+
+    /// Until the window is full, the samples are the first `count` entries of the buffer.
+    fn samples(&self) -> &[f64] {
+        &self.data[..self.count]
+    }
+}
+
+/// Share of slots that did not produce a block, over the last `CAP` observations of how far
+/// the slot and the block height advanced.
+#[derive(Debug, Clone, Default)]
+pub struct SkippedSlotRate<const CAP: usize> {
+    slots: RollingStats<CAP>,
+    skipped: RollingStats<CAP>,
+}
+
+impl<const CAP: usize> SkippedSlotRate<CAP> {
+    pub fn push(&mut self, slots: u64, blocks: u64) {
+        if slots == 0 {
+            return;
+        }
+
+        self.slots.push(slots as f64);
+        self.skipped.push(slots.saturating_sub(blocks) as f64);
+    }
+
+    pub fn rate(&self) -> f64 {
+        if self.slots.sum() <= 0.0 {
+            0.0
+        } else {
+            self.skipped.sum() / self.slots.sum()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_window_reports_zeroes() {
+        let stats = RollingStats::<4>::new();
+        assert_eq!(stats.count(), 0);
+        assert_eq!(stats.average(), 0.0);
+        assert_eq!(stats.min(), 0.0);
+        assert_eq!(stats.max(), 0.0);
+        assert_eq!(stats.percentile(50.0), 0.0);
+        assert!(stats.values().is_empty());
+    }
+
+    #[test]
+    fn nearest_rank_percentiles() {
+        let mut stats = RollingStats::<10>::new();
+        for val in [5.0, 1.0, 4.0, 2.0, 3.0] {
+            stats.push(val);
+        }
+
+        assert_eq!(stats.percentile(0.0), 1.0);
+        assert_eq!(stats.percentile(20.0), 1.0);
+        assert_eq!(stats.percentile(21.0), 2.0);
+        assert_eq!(stats.percentile(50.0), 3.0);
+        assert_eq!(stats.percentile(100.0), 5.0);
+        // Out of range percentiles are clamped to the smallest and largest sample.
+        assert_eq!(stats.percentile(-10.0), 1.0);
+        assert_eq!(stats.percentile(150.0), 5.0);
+
+        let mut single = RollingStats::<10>::new();
+        single.push(7.0);
+        assert_eq!(single.percentile(1.0), 7.0);
+        assert_eq!(single.percentile(99.0), 7.0);
+    }
+
+    #[test]
+    fn full_window_drops_the_oldest_samples() {
+        let mut stats = RollingStats::<3>::new();
+        for val in 1..=5 {
+            stats.push(val as f64);
+        }
+
+        assert_eq!(stats.count(), 3);
+        assert_eq!(stats.values(), vec![3.0, 4.0, 5.0]);
+        assert_eq!(stats.sum(), 12.0);
+        assert_eq!(stats.average(), 4.0);
+        assert_eq!(stats.min(), 3.0);
+        assert_eq!(stats.max(), 5.0);
+        assert_eq!(stats.percentile(50.0), 4.0);
+    }
+
+    #[test]
+    fn skipped_slot_rate() {
+        let mut rate = SkippedSlotRate::<4>::default();
+        assert_eq!(rate.rate(), 0.0);
+
+        // Observations without slot progress are ignored.
+        rate.push(0, 0);
+        assert_eq!(rate.rate(), 0.0);
+
+        rate.push(1, 1);
+        rate.push(3, 1);
+        assert_eq!(rate.rate(), 0.5);
+
+        // The block height catching up with more blocks than slots never counts negatively.
+        rate.push(2, 3);
+        assert_eq!(rate.rate(), 2.0 / 6.0);
+
+        rate.push(4, 4);
+        rate.push(4, 4);
+        rate.push(4, 4);
+        assert_eq!(rate.rate(), 0.0);
+    }
+}
@@ -0,0 +1,37 @@
+use std::collections::{HashSet, VecDeque};
+
+/// Bounded set of recently seen transaction signatures. Once the capacity is reached the
+/// oldest signature is forgotten, keeping memory constant for long running processes.
+#[derive(Debug, Clone)]
+pub struct SignatureCache {
+    capacity: usize,
+    order: VecDeque<String>,
+    seen: HashSet<String>,
+}
+
+impl SignatureCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            order: VecDeque::with_capacity(capacity),
+            seen: HashSet::with_capacity(capacity),
+        }
+    }
+
+    /// Returns `true` if the signature had not been seen before.
+    pub fn insert(&mut self, signature: &str) -> bool {
+        if self.seen.contains(signature) {
+            return false;
+        }
+
+        if self.order.len() == self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+
+        self.order.push_back(signature.to_string());
+        self.seen.insert(signature.to_string());
+        true
+    }
+}
@@ -0,0 +1,175 @@
+use tokio_tungstenite::{connect_async, tungstenite::Message as WsMessage};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use futures_util::{SinkExt, StreamExt};
+use std::time::{Duration, Instant};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::sources::SourceError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotFeedMode {
+    /// `slotSubscribe`, notified once the node has processed a slot.
+    Slot,
+    /// `slotsUpdatesSubscribe`, notified as soon as the node receives the first shred of a slot
+    /// and timestamped by the node itself. Unstable, not every provider supports it.
+    SlotsUpdates,
+}
+
+impl SlotFeedMode {
+    pub fn from_name(mode: &str) -> Option<SlotFeedMode> {
+        match mode.to_lowercase().as_str() {
+            "slot" => Some(SlotFeedMode::Slot),
+            "updates" | "slots_updates" => Some(SlotFeedMode::SlotsUpdates),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SlotEvent {
+    pub slot: u64,
+    pub received_at: Instant,
+    /// Unix timestamp in milliseconds reported by the node, only set by `SlotsUpdates`.
+    pub timestamp: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SlotNotificationValue {
+    slot: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SlotsUpdatesNotificationValue {
+    slot: u64,
+    timestamp: u64,
+    #[serde(rename = "type")]
+    update_type: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SlotNotificationParams<T> {
+    result: T,
+}
+
+#[derive(Debug, Deserialize)]
+struct SlotNotification<T> {
+    method: String,
+    params: SlotNotificationParams<T>,
+}
+
+/// A websocket feed of new slots, used to time slots from actual slot events rather than by
+/// polling for the latest blockhash.
+#[derive(Clone)]
+pub struct SlotFeed {
+    ws_url: String,
+    mode: SlotFeedMode,
+}
+
+impl SlotFeed {
+    pub fn new(ws_url: &str, mode: SlotFeedMode) -> Self {
+        Self {
+            ws_url: ws_url.to_string(),
+            mode,
+        }
+    }
+
+    /// Connects and subscribes, yielding one event per new slot for the lifetime of the
+    /// connection. The channel closing signals that `subscribe` should be called again.
+    pub async fn subscribe(&self) -> Result<UnboundedReceiver<SlotEvent>, SourceError> {
+        info!("Connecting to: {}", self.ws_url);
+        let (ws, _response) = connect_async(&self.ws_url).await?;
+        info!("WebSocket connection established!");
+
+        let (mut write, read) = ws.split();
+        let method = match self.mode {
+            SlotFeedMode::Slot => "slotSubscribe",
+            SlotFeedMode::SlotsUpdates => "slotsUpdatesSubscribe",
+        };
+        write.send(WsMessage::Text(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+        }).to_string())).await?;
+        info!("Subscription to {:?} notifications successfully sent.", self.mode);
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let mode = self.mode;
+        tokio::spawn(async move {
+            let heartbeat = tokio::spawn(async move {
+                loop {
+                    tokio::time::sleep(Duration::from_secs(30)).await;
+                    if write.send(WsMessage::Ping(vec![])).await.is_err() {
+                        break;
+                    }
+                }
+            });
+
+            process_messages(read, mode, sender).await;
+            heartbeat.abort();
+        });
+
+        Ok(receiver)
+    }
+}
+
+async fn process_messages<S>(mut read: S, mode: SlotFeedMode, sender: UnboundedSender<SlotEvent>)
+where
+    S: futures::Stream<Item = Result<WsMessage, tokio_tungstenite::tungstenite::Error>> + Unpin
+{
+    let mut last_slot: Option<u64> = None;
+
+    while let Some(message) = read.next().await {
+        let text = match message {
+            Ok(WsMessage::Text(text)) => text,
+            Ok(WsMessage::Close(frame)) => {
+                warn!("WebSocket connection closed: {:?}", frame);
+                break;
+            }
+            Ok(_) => continue,
+            Err(err) => {
+                warn!("Error processing slot messages: {}", err);
+                break;
+            }
+        };
+
+        let event = match mode {
+            SlotFeedMode::Slot => serde_json::from_str::<SlotNotification<SlotNotificationValue>>(&text)
+                .ok()
+                .filter(|notification| notification.method == "slotNotification")
+                .map(|notification| SlotEvent {
+                    slot: notification.params.result.slot,
+                    received_at: Instant::now(),
+                    timestamp: None,
+                }),
+            SlotFeedMode::SlotsUpdates => serde_json::from_str::<SlotNotification<SlotsUpdatesNotificationValue>>(&text)
+                .ok()
+                .filter(|notification| notification.method == "slotsUpdatesNotification")
+                .filter(|notification| notification.params.result.update_type == "firstShredReceived")
+                .map(|notification| SlotEvent {
+                    slot: notification.params.result.slot,
+                    received_at: Instant::now(),
+                    timestamp: Some(notification.params.result.timestamp),
+                }),
+        };
+
+        match event {
+            // Slots can be notified out of order (e.g. around forks), only new slots are relevant.
+            Some(event) if last_slot.map_or(true, |last| event.slot > last) => {
+                last_slot = Some(event.slot);
+                if sender.send(event).is_err() {
+                    break;
+                }
+            }
+            Some(_) => {}
+            None => {
+                if let Some(error) = serde_json::from_str::<Value>(&text).ok().and_then(|value| value.get("error").cloned()) {
+                    warn!("Slot subscription error: {}", error);
+                    break;
+                }
+            }
+        }
+    }
+}
@@ -15,9 +15,9 @@ use daos_fund::DaosFundInitializeCurveNotification;
 pub fn initialize_curve_handler(
     _slot: u64,
     instruction: &UiPartiallyDecodedInstruction,
-    _accounts: &Vec<ParsedAccount>,
+    _accounts: &[ParsedAccount],
     _meta: &UiTransactionStatusMeta,
-    _signature: &String,
+    _signature: &str,
     tx: UnboundedSender<MpscMessage>
 ) {
     let token_address = instruction.accounts.get(1).unwrap();
@@ -0,0 +1,253 @@
+/// # Failed Instruction Detection
+/// This module reports transactions that failed on chain while invoking one of the programs in
+/// `ProgramId`, e.g. pump.fun buys that lost the race for a new token or exceeded their slippage.
+/// Custom error codes are mapped to the names from each program's error table.
+///
+/// Only active when failed transactions are ingested (`INCLUDE_FAILED_TRANSACTIONS`).
+///
+/// REQUIRES REDIS: FALSE
+///
+/// REQUIRES ZMQ: TRUE
+/// - Failed instructions.
+
+use solana_transaction_status::{
+    option_serializer::OptionSerializer,
+    parse_accounts::ParsedAccount,
+    UiInstruction,
+    UiParsedInstruction,
+    UiTransactionStatusMeta
+};
+use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+use tokio::sync::mpsc::UnboundedSender;
+use prost::Message;
+use tracing::info;
+use yansi::Paint;
+use chrono::Utc;
+
+use crate::{constants::zmq::FAILED_INSTRUCTION, messaging::MpscMessage, programs::ProgramId};
+
+pub mod failed_instruction {
+    tonic::include_proto!("failed_instruction");
+}
+
+use failed_instruction::FailedInstruction;
+
+/// `instructions` holds the failing top-level instruction followed by its inner instructions.
+/// The deepest tracked program among them is reported, since tracked programs such as pump.fun
+/// are frequently invoked through a bot's own program.
+pub fn failed_instruction_handler(
+    slot: u64,
+    error: &TransactionError,
+    instructions: &[UiInstruction],
+    accounts: &[ParsedAccount],
+    meta: &UiTransactionStatusMeta,
+    signature: &str,
+    tx: UnboundedSender<MpscMessage>
+) {
+    let (index, instruction_error) = match error {
+        TransactionError::InstructionError(index, instruction_error) => (*index, instruction_error),
+        _ => return,
+    };
+
+    let (program_id, instruction) = match instructions.iter().rev().find_map(|instruction| match instruction {
+        UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(instruction)) => {
+            ProgramId::from_address(&instruction.program_id).map(|program_id| (program_id, instruction))
+        }
+        _ => None,
+    }) {
+        Some(tracked) => tracked,
+        None => return,
+    };
+
+    // The error may have been raised by a program further down the call stack (e.g. the token
+    // program on insufficient funds), in which case the code belongs to that program's table.
+    let raised_by_program = failing_program(meta)
+        .map(|failing| failing == instruction.program_id)
+        .unwrap_or(true);
+
+    let (custom_error, error_code, error_name) = match instruction_error {
+        InstructionError::Custom(code) => {
+            let name = if raised_by_program { program_id.error_name(*code) } else { None };
+            (true, *code, name.unwrap_or_default().to_string())
+        }
+        other => (false, 0, format!("{:?}", other)),
+    };
+
+    let signer = accounts.iter()
+        .find(|account| account.signer)
+        .map(|account| account.pubkey.clone())
+        .unwrap_or_default();
+
+    let instruction_name = program_id.instruction_name(&instruction.data).unwrap_or("unknown");
+
+    let message = FailedInstruction {
+        signature: signature.to_string(),
+        slot,
+        program: program_id.name().to_string(),
+        program_id: instruction.program_id.clone(),
+        instruction: instruction_name.to_string(),
+        instruction_index: index as u32,
+        signer: signer.clone(),
+        custom_error,
+        error_code,
+        error_name: error_name.clone(),
+        error: format!("{:?}", error),
+        timestamp: Utc::now().timestamp_millis(),
+    };
+
+    tx.send(MpscMessage {
+        topic: FAILED_INSTRUCTION.to_string(),
+        payload: message.encode_to_vec(),
+    }).expect("Failed to send MPSC Message.");
+
+    info!(
+        "Sending {} for {} {} by {} ({})",
+        Paint::red("FAILED_INSTRUCTION"),
+        Paint::cyan(program_id.name()),
+        Paint::magenta(instruction_name),
+        Paint::black(signer),
+        if error_name.is_empty() { format!("{:?}", instruction_error) } else { error_name }
+    );
+}
+
+/// The program that raised the error, taken from the first `Program <id> failed: ...` log line.
+/// The failure then propagates through every calling program, each logging its own `failed`
+/// line, so the innermost program is logged first.
+fn failing_program(meta: &UiTransactionStatusMeta) -> Option<&str> {
+    let log_messages = match &meta.log_messages {
+        OptionSerializer::Some(log_messages) => log_messages,
+        _ => return None,
+    };
+
+    log_messages.iter().find_map(|line| {
+        line.strip_prefix("Program ")
+            .and_then(|rest| rest.split_once(" failed"))
+            .map(|(program_id, _)| program_id)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use solana_transaction_status::UiPartiallyDecodedInstruction;
+    use tokio::sync::mpsc;
+
+    use crate::programs::pump_fun::PUMP_FUN;
+
+    const BOT_PROGRAM_ID: &str = "Bot1111111111111111111111111111111111111111";
+    const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
+    fn instruction(program_id: &str, data: &[u8]) -> UiInstruction {
+        UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(UiPartiallyDecodedInstruction {
+            program_id: program_id.to_string(),
+            accounts: vec![],
+            data: bs58::encode(data).into_string(),
+            stack_height: None,
+        }))
+    }
+
+    fn pump_fun_buy() -> UiInstruction {
+        instruction(PUMP_FUN.addresses.program_id, &[&PUMP_FUN.discriminators.buy.to_be_bytes()[..], &[0; 16]].concat())
+    }
+
+    fn meta(log_messages: &[String]) -> UiTransactionStatusMeta {
+        serde_json::from_value(json!({
+            "err": null,
+            "status": { "Ok": null },
+            "fee": 5000,
+            "preBalances": [],
+            "postBalances": [],
+            "logMessages": log_messages,
+        })).unwrap()
+    }
+
+    fn failed_log(program_id: &str) -> String {
+        format!("Program {} failed: custom program error: 0x1771", program_id)
+    }
+
+    fn signer() -> ParsedAccount {
+        ParsedAccount { pubkey: "Signer1111111111111111111111111111111111111".to_string(), writable: true, signer: true, source: None }
+    }
+
+    /// Runs the handler and returns the reported failure, if any.
+    fn report(error: TransactionError, instructions: &[UiInstruction], meta: &UiTransactionStatusMeta) -> Option<FailedInstruction> {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        failed_instruction_handler(42, &error, instructions, &[signer()], meta, "signature", tx);
+
+        rx.try_recv().ok().map(|message| {
+            assert_eq!(message.topic, FAILED_INSTRUCTION);
+            FailedInstruction::decode(message.payload.as_slice()).unwrap()
+        })
+    }
+
+    fn custom(index: u8, code: u32) -> TransactionError {
+        TransactionError::InstructionError(index, InstructionError::Custom(code))
+    }
+
+    #[test]
+    fn finds_the_innermost_failing_program() {
+        let logs = [
+            "Program Bot1111111111111111111111111111111111111111 invoke [1]".to_string(),
+            format!("Program {} invoke [2]", PUMP_FUN.addresses.program_id),
+            format!("Program {} invoke [3]", TOKEN_PROGRAM_ID),
+            failed_log(TOKEN_PROGRAM_ID),
+            failed_log(PUMP_FUN.addresses.program_id),
+            failed_log(BOT_PROGRAM_ID),
+        ];
+        assert_eq!(failing_program(&meta(&logs)), Some(TOKEN_PROGRAM_ID));
+        assert_eq!(failing_program(&meta(&logs[..2])), None);
+
+        let mut without_logs = meta(&[]);
+        without_logs.log_messages = OptionSerializer::None;
+        assert_eq!(failing_program(&without_logs), None);
+    }
+
+    #[test]
+    fn attributes_the_error_to_the_deepest_tracked_program() {
+        // A bot's program invoking pump.fun, which raised TooLittleSolReceived itself.
+        let instructions = [instruction(BOT_PROGRAM_ID, &[1]), pump_fun_buy()];
+        let logs = [failed_log(PUMP_FUN.addresses.program_id), failed_log(BOT_PROGRAM_ID)];
+        let failure = report(custom(2, 6003), &instructions, &meta(&logs)).unwrap();
+
+        assert_eq!(failure.slot, 42);
+        assert_eq!(failure.program, "pump_fun");
+        assert_eq!(failure.program_id, PUMP_FUN.addresses.program_id);
+        assert_eq!(failure.instruction, "buy");
+        assert_eq!(failure.instruction_index, 2);
+        assert_eq!(failure.signer, signer().pubkey);
+        assert!(failure.custom_error);
+        assert_eq!(failure.error_code, 6003);
+        assert_eq!(failure.error_name, "TooLittleSolReceived");
+
+        // Raydium swapping through pump.fun is reported as pump.fun, the innermost of both.
+        let instructions = [instruction(ProgramId::Raydium.address(), &[9]), pump_fun_buy()];
+        let logs = [failed_log(PUMP_FUN.addresses.program_id), failed_log(ProgramId::Raydium.address())];
+        assert_eq!(report(custom(0, 6003), &instructions, &meta(&logs)).unwrap().program, "pump_fun");
+    }
+
+    #[test]
+    fn does_not_name_codes_raised_by_another_program() {
+        // The token program failed with its own code 1 (insufficient funds) inside pump.fun.
+        let logs = [failed_log(TOKEN_PROGRAM_ID), failed_log(PUMP_FUN.addresses.program_id)];
+        let failure = report(custom(0, 6001), &[pump_fun_buy()], &meta(&logs)).unwrap();
+        assert_eq!(failure.program, "pump_fun");
+        assert_eq!(failure.error_code, 6001);
+        assert_eq!(failure.error_name, "");
+
+        // Without logs the code is assumed to belong to the tracked program.
+        let failure = report(custom(0, 6001), &[pump_fun_buy()], &meta(&[])).unwrap();
+        assert_eq!(failure.error_name, "AlreadyInitialized");
+    }
+
+    #[test]
+    fn reports_builtin_errors_and_ignores_untracked_programs() {
+        let error = TransactionError::InstructionError(0, InstructionError::InsufficientFunds);
+        let failure = report(error, &[pump_fun_buy()], &meta(&[])).unwrap();
+        assert!(!failure.custom_error);
+        assert_eq!(failure.error_name, "InsufficientFunds");
+
+        assert!(report(custom(0, 1), &[instruction(BOT_PROGRAM_ID, &[1])], &meta(&[])).is_none());
+        assert!(report(TransactionError::AccountInUse, &[pump_fun_buy()], &meta(&[])).is_none());
+    }
+}
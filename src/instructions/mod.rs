@@ -1,4 +1,5 @@
 pub mod daos_fund;
+pub mod failed;
 pub mod pump_fun;
 pub mod raydium;
 pub mod system;
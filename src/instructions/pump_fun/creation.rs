@@ -38,9 +38,9 @@ struct BaseMetadata {
 pub fn creation_handler(
     _slot: u64,
     instruction: &UiPartiallyDecodedInstruction,
-    _accounts: &Vec<ParsedAccount>,
+    _accounts: &[ParsedAccount],
     meta: &UiTransactionStatusMeta,
-    signature: &String,
+    signature: &str,
     tx: UnboundedSender<MpscMessage>
 ) {
     let bytes = bs58::decode(&instruction.data)
@@ -62,7 +62,7 @@ pub fn creation_handler(
         }
     };
 
-    let token_account = instruction.accounts.get(0).unwrap();
+    let token_account = instruction.accounts.first().unwrap();
     let bonding_curve = instruction.accounts.get(2).unwrap();
     let associated_bonding_curve = instruction.accounts.get(3).unwrap();
     let deployer = instruction.accounts.get(7).unwrap();
@@ -74,7 +74,7 @@ pub fn creation_handler(
         }
     };
 
-    let owner_balance = match find_token_balance_by_address(post_token_balances, &deployer) {
+    let owner_balance = match find_token_balance_by_address(post_token_balances, deployer) {
         Some(balance) => balance.ui_token_amount.ui_amount.unwrap_or(0.0),
         None => 0.0
     };
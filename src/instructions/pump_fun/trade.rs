@@ -5,11 +5,17 @@
 /// 
 /// REQUIRES REDIS: TRUE
 /// - Tracked tokens.
+///
 /// REQUIRES ZMQ: TRUE
 /// - Token migrations started.
 /// - Token bonding curve updates.
 
-use solana_transaction_status::{parse_accounts::ParsedAccount, UiPartiallyDecodedInstruction, UiTransactionStatusMeta};
+use solana_transaction_status::{
+    option_serializer::OptionSerializer,
+    parse_accounts::ParsedAccount,
+    UiPartiallyDecodedInstruction,
+    UiTransactionStatusMeta
+};
 use tokio_tungstenite::tungstenite::Error as WsError;
 use tokio::sync::mpsc::UnboundedSender;
 use deadpool_redis::Pool;
@@ -45,20 +51,39 @@ const LIQUIDITY_POOL_TOKEN_FUNDING_AMOUNT: u64 = INITIAL_TOKEN_TOTAL_SUPPLY - IN
 const VIRTUAL_LIQUIDITY_TOKEN_SEED: u64 = INITIAL_VIRTUAL_TOKEN_RESERVES - INITIAL_REAL_TOKEN_RESERVES;
 
 pub async fn trade_handler(
+    slot: u64,
     instruction: &UiPartiallyDecodedInstruction,
-    accounts: &Vec<ParsedAccount>,
+    accounts: &[ParsedAccount],
     meta: &UiTransactionStatusMeta,
     redis_pool: Arc<Pool>,
     tx: UnboundedSender<MpscMessage>
 ) -> Result<(), WsError> {
-    let token_address = instruction.accounts.get(2).unwrap().to_string();
+    let (token_address, bonding_curve) = match (instruction.accounts.get(2), instruction.accounts.get(3)) {
+        (Some(token_address), Some(bonding_curve)) => (token_address.to_string(), bonding_curve),
+        _ => {
+            return Err(WsError::Io(io::Error::new(io::ErrorKind::InvalidData, "Trade instruction is missing accounts")));
+        }
+    };
 
-    let bonding_curve = instruction.accounts.get(3).unwrap();
-    let bonding_curve_index = accounts.iter().position(|x| x.pubkey == *bonding_curve).unwrap();
+    // With address lookup tables the bonding curve is usually one of the loaded addresses,
+    // which the account list only contains once they have been resolved.
+    let real_lamport_reserves = match accounts
+        .iter()
+        .position(|x| x.pubkey == *bonding_curve)
+        .and_then(|index| meta.post_balances.get(index))
+    {
+        Some(balance) => balance,
+        None => {
+            warn!("Bonding curve {} not found in transaction accounts, skipping trade.", Paint::black(bonding_curve));
+            return Ok(());
+        }
+    };
 
-    let post_token_balances = meta.post_token_balances.clone().unwrap();
+    let post_token_balances = match &meta.post_token_balances {
+        OptionSerializer::Some(balances) => balances.clone(),
+        _ => Vec::new(),
+    };
 
-    let real_lamport_reserves = meta.post_balances.get(bonding_curve_index).unwrap();
     let bonding_curve_token_balance = match find_token_balance_by_address(&post_token_balances, bonding_curve) {
         Some(token_balance) => token_balance.ui_token_amount.amount.parse::<u64>().unwrap_or(0),
         None => 0
@@ -87,12 +112,13 @@ pub async fn trade_handler(
 
     // todo: For now we'll just assume we need a set of tracked tokens, instead of everything.
     let mut conn = redis_pool.get().await.map_err(|e| {
-        WsError::Io(io::Error::new(io::ErrorKind::Other, format!("Failed to get Redis connection: {:?}", e)));
-    }).expect("Failed to get Redis connection.");
+        WsError::Io(io::Error::new(io::ErrorKind::Other, format!("Failed to get Redis connection: {:?}", e)))
+    })?;
 
-    let is_tracked: bool = conn.sismember(TRACKED_TOKEN_ADDRESSES, &token_address).await.map_err(|e| {
-        WsError::Io(io::Error::new(io::ErrorKind::Other, format!("Failed to check if token is tracked: {:?}", e)));
-    }).unwrap_or(false);
+    let is_tracked: bool = conn.sismember(TRACKED_TOKEN_ADDRESSES, &token_address).await.unwrap_or_else(|e| {
+        warn!("Failed to check if token is tracked: {:?}", e);
+        false
+    });
 
     if !is_tracked {
         return Ok(());
@@ -104,7 +130,11 @@ pub async fn trade_handler(
         real_lamport_reserves: *real_lamport_reserves,
         real_token_reserves,
         virtual_lamport_reserves,
-        virtual_token_reserves
+        virtual_token_reserves,
+        token_total_supply: INITIAL_TOKEN_TOTAL_SUPPLY,
+        complete: real_token_reserves == 0,
+        slot,
+        exact: false,
     };
 
     tx.send(MpscMessage {
@@ -6,8 +6,8 @@ use crate::constants::addresses::PUMP_FUN_RAYDIUM_MIGRATION;
 
 pub fn initialize_two_handler(
     instruction: &UiPartiallyDecodedInstruction,
-    accounts: &Vec<ParsedAccount>,
-    _signature: &String
+    accounts: &[ParsedAccount],
+    _signature: &str
 ) {
     let is_pump_fun = accounts.iter()
         .find(|account| account.pubkey == PUMP_FUN_RAYDIUM_MIGRATION)
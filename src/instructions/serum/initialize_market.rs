@@ -51,9 +51,9 @@ impl InitializeMarketData {
 
 pub async fn initialize_market_handler(
     instruction: &UiPartiallyDecodedInstruction,
-    accounts: &Vec<ParsedAccount>,
+    accounts: &[ParsedAccount],
     _tx: UnboundedSender<MpscMessage>,
-    _signature: &String,
+    _signature: &str,
     serum_market_client: SerumMarketClient
 ) {
     let start = Instant::now();
@@ -68,7 +68,7 @@ pub async fn initialize_market_handler(
     if is_pump_fun {
         let data = InitializeMarketData::from_base58_data(&instruction.data);
 
-        let market_id_address = instruction.accounts.get(0).unwrap();
+        let market_id_address = instruction.accounts.first().unwrap();
         let request_queue_address = instruction.accounts.get(1).unwrap();
         let event_queue_address = instruction.accounts.get(2).unwrap();
         let bids_address = instruction.accounts.get(3).unwrap();
@@ -1,11 +1,22 @@
 use deadpool_redis::{Config, Runtime};
 use gimpey_db_gateway::SerumMarketClient;
 use processors::blockhashes::BlockhashProcessor;
+use processors::blockhash_service::{BlockhashGrpcService, BlockhashServiceServer};
+use processors::commitments::CommitmentTracker;
+use processors::bonding_curves::BondingCurveProcessor;
+use processors::leaders::LeaderScheduleProcessor;
+use processors::priority_fees::PriorityFeeEstimator;
+use processors::priority_fee_service::{PriorityFeeGrpcService, PriorityFeeServiceServer};
+use processors::node_state::NodeState;
+use processors::rpc_emulator::RpcEmulator;
+use sources::filter::watch_tracked_sets;
+use helpers::address_lookup_tables::AddressLookupTableCache;
+use helpers::slot_feed::{SlotFeed, SlotFeedMode};
 use tokio_tungstenite::tungstenite::Error as WsError;
 use tracing_subscriber::EnvFilter;
 use tokio::sync::mpsc;
 use dotenv::dotenv;
-use tracing::info;
+use tracing::{error, info, warn};
 use std::{env, io, sync::Arc};
 
 pub mod transaction_helpers;
@@ -14,6 +25,7 @@ pub mod processors;
 pub mod messaging;
 pub mod constants;
 pub mod programs;
+pub mod sources;
 pub mod helpers;
 
 #[tokio::main]
@@ -28,12 +40,10 @@ async fn main() -> Result<(), WsError> {
 
     info!("Starting the Helius Node Emulator microservice...");
 
-    let api_key = env::var("HELIUS_RPC_API_KEY").expect("HELIUS_RPC_API_KEY must be set");
-
     let (tx, mut rx) = mpsc::unbounded_channel::<messaging::MpscMessage>();
 
     let redis_url = env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string());
-    let cfg = Config::from_url(redis_url);
+    let cfg = Config::from_url(redis_url.clone());
     let pool = cfg.create_pool(Some(Runtime::Tokio1)).expect("Failed to create pool");
     let pool = Arc::new(pool);
 
@@ -59,29 +69,216 @@ async fn main() -> Result<(), WsError> {
         }
     });
 
+    // Slots are timed from `SLOT_WS_URL` when set, `SLOT_SUBSCRIPTION` selects `slot` (default)
+    // or `updates` for `slotsUpdatesSubscribe`.
+    let slot_feed = env::var("SLOT_WS_URL").ok().map(|ws_url| {
+        let mode_name = env::var("SLOT_SUBSCRIPTION").unwrap_or_else(|_| "slot".to_string());
+        let mode = SlotFeedMode::from_name(&mode_name).expect("Unknown SLOT_SUBSCRIPTION mode");
+        SlotFeed::new(&ws_url, mode)
+    });
+
+    let blockhash_endpoints = processors::blockhashes::endpoints_from_env().expect("Failed to configure blockhash RPC endpoints");
+    let blockhash_expirations = processors::blockhashes::expirations_from_env().expect("Invalid BLOCKHASH_EXPIRATIONS");
+    let blockhash_stale_after = processors::blockhashes::stale_after_from_env().expect("Invalid BLOCKHASH_STALE_AFTER_MS");
+
     let mut blockhash_processor = BlockhashProcessor::new(
-        pool.clone()
+        pool.clone(),
+        slot_feed,
+        blockhash_endpoints,
+        blockhash_expirations,
+        blockhash_stale_after,
+        tx.clone()
     ).await.map_err(|e: reqwest::Error| {
         WsError::Io(io::Error::new(io::ErrorKind::Other, e.to_string()))
     })?;
 
+    // Upcoming leaders are tracked when `LEADER_SCHEDULE_RPC_URL` is set, `LEADER_LOOKAHEAD`
+    // sets how many leaders following the current one are published (defaults to 8).
+    let leader_schedule_processor = env::var("LEADER_SCHEDULE_RPC_URL").ok().map(|rpc_url| {
+        let lookahead = env::var("LEADER_LOOKAHEAD")
+            .map(|lookahead| lookahead.parse().expect("Invalid LEADER_LOOKAHEAD"))
+            .unwrap_or(8);
+        LeaderScheduleProcessor::new(&rpc_url, pool.clone(), blockhash_processor.subscribe_slots(), lookahead, tx.clone())
+    });
+
+    // Exact bonding curve state, requires a websocket endpoint supporting `accountSubscribe`.
+    // A single watcher follows the tracked sets for both the bonding curve subscriptions and
+    // the transaction source filter, and stops once neither needs it.
+    let tracked_sets = watch_tracked_sets(&redis_url);
+
+    let bonding_curve_processor = env::var("BONDING_CURVE_WS_URL").ok().map(|ws_url| {
+        BondingCurveProcessor::new(&ws_url, pool.clone(), tracked_sets.clone(), tx.clone())
+    });
+
+    let backfiller = sources::backfiller_from_env().map(Arc::new);
+    let transaction_source = sources::source_from_env(pool.clone(), tracked_sets, backfiller.clone())
+        .expect("Failed to configure transaction source");
+
+    // A subset of the Solana JSON-RPC is served from the observed state when `RPC_EMULATOR_ADDR`
+    // (e.g. `0.0.0.0:8899`) is set.
+    let rpc_emulator_addr = env::var("RPC_EMULATOR_ADDR")
+        .ok()
+        .map(|addr| addr.parse::<std::net::SocketAddr>().expect("Invalid RPC_EMULATOR_ADDR"));
+    let node_state = rpc_emulator_addr.map(|_| NodeState::new());
+    let rpc_emulator = rpc_emulator_addr.zip(node_state.clone()).map(|(addr, node_state)| (addr, RpcEmulator::new(
+        node_state,
+        blockhash_processor.subscribe(),
+        blockhash_processor.subscribe_slots(),
+        pool.clone()
+    )));
+
+    // Following the commitment of published transactions is optional, since it costs an
+    // additional `getSignatureStatuses` request every couple of seconds. The JSON-RPC emulator
+    // requires it, signature statuses would otherwise never move past the source's commitment.
+    let commitment_tracker = env::var("COMMITMENT_RPC_URL")
+        .ok()
+        .map(|rpc_url| CommitmentTracker::new(&rpc_url, node_state.clone(), tx.clone()));
+    assert!(
+        rpc_emulator.is_none() || commitment_tracker.is_some(),
+        "RPC_EMULATOR_ADDR requires COMMITMENT_RPC_URL to report signature statuses."
+    );
+
+    // Only required for providers that deliver v0 transactions without their loaded addresses.
+    let lookup_tables = env::var("LOOKUP_TABLE_RPC_URL")
+        .ok()
+        .map(|rpc_url| AddressLookupTableCache::new(&rpc_url));
+
+    // Priority fee recommendations derived from the transaction stream, unless
+    // `PRIORITY_FEE_ESTIMATES` is set to `false`.
+    let priority_fee_estimator = match env::var("PRIORITY_FEE_ESTIMATES").as_deref() {
+        Ok("false") => None,
+        _ => Some(PriorityFeeEstimator::new(pool.clone())),
+    };
+
     let transaction_processor = processors::transactions::TransactionProcessor::new(
-        &api_key, 
-        "atlas-mainnet.helius-rpc.com",
+        transaction_source,
+        backfiller,
+        commitment_tracker.clone(),
+        lookup_tables,
+        priority_fee_estimator.clone(),
+        node_state,
+        bonding_curve_processor.is_none(),
         tx, 
         pool,
         serum_market_client.clone()
     ).await?;
 
+    // The blockhash gRPC service is exposed when `BLOCKHASH_GRPC_ADDR` (e.g. `0.0.0.0:50051`) is
+    // set, the priority fee service on `PRIORITY_FEE_GRPC_ADDR`, which defaults to the same
+    // address. Services sharing an address are served by the same server.
+    let blockhash_grpc_addr = env::var("BLOCKHASH_GRPC_ADDR")
+        .ok()
+        .map(|addr| addr.parse::<std::net::SocketAddr>().expect("Invalid BLOCKHASH_GRPC_ADDR"));
+    let priority_fee_grpc_addr = env::var("PRIORITY_FEE_GRPC_ADDR")
+        .ok()
+        .map(|addr| addr.parse::<std::net::SocketAddr>().expect("Invalid PRIORITY_FEE_GRPC_ADDR"))
+        .or(blockhash_grpc_addr);
+
+    match (&priority_fee_estimator, priority_fee_grpc_addr) {
+        (Some(_), None) => warn!(
+            "Priority fee estimates are only stored in Redis, set PRIORITY_FEE_GRPC_ADDR or BLOCKHASH_GRPC_ADDR to serve them over gRPC."
+        ),
+        (None, Some(_)) if env::var("PRIORITY_FEE_GRPC_ADDR").is_ok() => warn!(
+            "PRIORITY_FEE_GRPC_ADDR is set but PRIORITY_FEE_ESTIMATES is disabled, the priority fee service is not served."
+        ),
+        _ => {}
+    }
+
+    let mut grpc_addrs: Vec<std::net::SocketAddr> = blockhash_grpc_addr.into_iter()
+        .chain(priority_fee_estimator.as_ref().and(priority_fee_grpc_addr))
+        .collect();
+    grpc_addrs.dedup();
+
+    let grpc_servers: Vec<_> = grpc_addrs.into_iter().map(|addr| {
+        let blockhash_service = (blockhash_grpc_addr == Some(addr))
+            .then(|| BlockhashGrpcService::new(blockhash_processor.subscribe(), blockhash_processor.subscribe_status()));
+        let priority_fee_service = priority_fee_estimator.clone()
+            .filter(|_| priority_fee_grpc_addr == Some(addr))
+            .map(PriorityFeeGrpcService::new);
+
+        async move {
+            if blockhash_service.is_some() {
+                info!("Serving blockhashes over gRPC on {}", addr);
+            }
+            if priority_fee_service.is_some() {
+                info!("Serving priority fee estimates over gRPC on {}", addr);
+            }
+
+            if let Err(err) = tonic::transport::Server::builder()
+                .add_optional_service(blockhash_service.map(BlockhashServiceServer::new))
+                .add_optional_service(priority_fee_service.map(PriorityFeeServiceServer::new))
+                .serve(addr)
+                .await
+            {
+                error!("gRPC server on {} stopped: {}", addr, err);
+            }
+        }
+    }).collect();
+
+    let grpc_server_task = tokio::spawn(futures::future::join_all(grpc_servers));
+
+    let blockhash_watchdog = blockhash_processor.clone();
+    let blockhash_watchdog_task = tokio::spawn(async move {
+        blockhash_watchdog.start_staleness_watchdog().await;
+    });
+
+    let blockhash_status = blockhash_processor.clone();
+    let blockhash_status_task = tokio::spawn(async move {
+        blockhash_status.start_status_reporter().await;
+    });
+
     let blockhash_processor_task = tokio::spawn(async move {
-        let _ = blockhash_processor.start_processor().await;
+        blockhash_processor.start_processor().await;
     });
 
     let transaction_processor_task = tokio::spawn(async move {
         transaction_processor.start_processor().await;
     });
 
-    let _ = tokio::join!(blockhash_processor_task, transaction_processor_task);
+    let commitment_tracker_task = tokio::spawn(async move {
+        if let Some(commitment_tracker) = commitment_tracker {
+            commitment_tracker.start_tracker().await;
+        }
+    });
+
+    let leader_schedule_processor_task = tokio::spawn(async move {
+        if let Some(leader_schedule_processor) = leader_schedule_processor {
+            leader_schedule_processor.start_processor().await;
+        }
+    });
+
+    let priority_fee_estimator_task = tokio::spawn(async move {
+        if let Some(priority_fee_estimator) = priority_fee_estimator {
+            priority_fee_estimator.start_publisher().await;
+        }
+    });
+
+    let rpc_emulator_task = tokio::spawn(async move {
+        if let Some((addr, rpc_emulator)) = rpc_emulator {
+            if let Err(err) = rpc_emulator.serve(addr).await {
+                error!("JSON-RPC emulator stopped: {}", err);
+            }
+        }
+    });
+
+    let bonding_curve_processor_task = tokio::spawn(async move {
+        if let Some(bonding_curve_processor) = bonding_curve_processor {
+            bonding_curve_processor.start_processor().await;
+        }
+    });
+
+    let _ = tokio::join!(
+        blockhash_processor_task,
+        blockhash_watchdog_task,
+        blockhash_status_task,
+        transaction_processor_task,
+        commitment_tracker_task,
+        bonding_curve_processor_task,
+        leader_schedule_processor_task,
+        priority_fee_estimator_task,
+        rpc_emulator_task,
+        grpc_server_task
+    );
 
     Ok(())
 }
@@ -0,0 +1,121 @@
+/// # Blockhash gRPC Service
+/// Serves the blockhashes observed by the `BlockhashProcessor`, along with their freshness and
+/// estimated remaining validity, to transaction senders. `WatchBlockhash` pushes every new
+/// blockhash as it is observed, so senders no longer need to poll the Redis keys.
+
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+use tokio::sync::{mpsc, watch};
+use futures::Stream;
+use std::pin::Pin;
+
+use super::blockhashes::{BlockhashSnapshot, ProcessorState, ProcessorStatus, SlotBlockhash};
+
+pub mod blockhash {
+    tonic::include_proto!("blockhash");
+}
+
+use blockhash::{
+    blockhash_service_server::BlockhashService,
+    BlockhashWithExpiry,
+    GetBlockhashWithExpiryRequest,
+    GetLatestBlockhashRequest,
+    WatchBlockhashRequest
+};
+
+pub use blockhash::blockhash_service_server::BlockhashServiceServer;
+
+pub struct BlockhashGrpcService {
+    snapshots: watch::Receiver<BlockhashSnapshot>,
+    status: watch::Receiver<ProcessorStatus>,
+}
+
+impl BlockhashGrpcService {
+    pub fn new(snapshots: watch::Receiver<BlockhashSnapshot>, status: watch::Receiver<ProcessorStatus>) -> Self {
+        Self { snapshots, status }
+    }
+
+    fn select(&self, seconds: Option<f64>) -> Option<BlockhashWithExpiry> {
+        select(&self.snapshots.borrow(), seconds, is_degraded(&self.status))
+    }
+}
+
+fn is_degraded(status: &watch::Receiver<ProcessorStatus>) -> bool {
+    status.borrow().state == ProcessorState::Degraded
+}
+
+fn to_message(snapshot: &BlockhashSnapshot, entry: &SlotBlockhash, degraded: bool) -> BlockhashWithExpiry {
+    BlockhashWithExpiry {
+        blockhash: entry.blockhash.clone(),
+        slot: entry.slot,
+        last_valid_block_height: entry.last_valid_block_height,
+        observed_at: entry.observed_at,
+        seconds_remaining: snapshot.seconds_remaining(entry),
+        current_block_height: snapshot.current_block_height,
+        degraded,
+    }
+}
+
+/// The latest blockhash, or the one closest to `seconds` of remaining validity when set.
+fn select(snapshot: &BlockhashSnapshot, seconds: Option<f64>, degraded: bool) -> Option<BlockhashWithExpiry> {
+    let entry = match seconds {
+        Some(seconds) => snapshot.closest_to_expiry(seconds),
+        None => snapshot.latest(),
+    };
+
+    entry.map(|entry| to_message(snapshot, entry, degraded))
+}
+
+#[tonic::async_trait]
+impl BlockhashService for BlockhashGrpcService {
+    type WatchBlockhashStream = Pin<Box<dyn Stream<Item = Result<BlockhashWithExpiry, Status>> + Send>>;
+
+    async fn get_latest_blockhash(
+        &self,
+        _request: Request<GetLatestBlockhashRequest>
+    ) -> Result<Response<BlockhashWithExpiry>, Status> {
+        self.select(None)
+            .map(Response::new)
+            .ok_or_else(|| Status::unavailable("No blockhash has been observed yet"))
+    }
+
+    async fn get_blockhash_with_expiry(
+        &self,
+        request: Request<GetBlockhashWithExpiryRequest>
+    ) -> Result<Response<BlockhashWithExpiry>, Status> {
+        let seconds = request.into_inner().seconds;
+        if !seconds.is_finite() || seconds < 0.0 {
+            return Err(Status::invalid_argument("seconds must be a positive number"));
+        }
+
+        self.select(Some(seconds))
+            .map(Response::new)
+            .ok_or_else(|| Status::unavailable("No valid blockhash is available yet"))
+    }
+
+    async fn watch_blockhash(
+        &self,
+        request: Request<WatchBlockhashRequest>
+    ) -> Result<Response<Self::WatchBlockhashStream>, Status> {
+        let seconds = request.into_inner().seconds;
+        let mut snapshots = self.snapshots.clone();
+        let status = self.status.clone();
+        let (sender, receiver) = mpsc::channel(16);
+
+        tokio::spawn(async move {
+            // The current blockhash is sent right away, every later one as it is observed.
+            snapshots.mark_changed();
+
+            while snapshots.changed().await.is_ok() {
+                let message = select(&snapshots.borrow_and_update(), seconds, is_degraded(&status));
+                if let Some(message) = message {
+                    if sender.send(Ok(message)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(receiver)) as Self::WatchBlockhashStream))
+    }
+}
@@ -0,0 +1,285 @@
+/// # Pump Fun Bonding Curve Accounts
+/// Subscribes to the `BondingCurve` account of every tracked pump.fun token through
+/// `accountSubscribe` and publishes its decoded state. Unlike the reserves derived by the trade
+/// handler from post token balances, these are the exact values stored on chain. The derived
+/// reserves are no longer published while this processor runs, so that both never interleave
+/// on the same topic.
+///
+/// REQUIRES REDIS: TRUE
+/// - Tracked tokens.
+///
+/// REQUIRES ZMQ: TRUE
+/// - Token bonding curve updates.
+
+use tokio_tungstenite::{connect_async, tungstenite::Message as WsMessage};
+use base64::{prelude::BASE64_STANDARD, Engine};
+use futures_util::{SinkExt, StreamExt};
+use tokio::sync::{mpsc::UnboundedSender, watch};
+use std::{collections::{HashMap, HashSet}, sync::Arc, time::Duration};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use deadpool_redis::Pool;
+use redis::AsyncCommands;
+use tracing::{info, warn};
+use prost::Message;
+use yansi::Paint;
+
+use crate::{
+    constants::{redis::TRACKED_TOKEN_ADDRESSES, zmq::PUMP_FUN_BONDING_CURVE_UPDATE},
+    messaging::MpscMessage,
+    programs::pump_fun::BondingCurve,
+    sources::{filter::FilterChanges, SourceError}
+};
+
+pub mod spl_token {
+    tonic::include_proto!("spl_token");
+}
+
+use spl_token::PumpFunBondingCurveUpdate;
+
+#[derive(Debug, Deserialize)]
+struct AccountNotificationContext {
+    slot: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccountNotificationValue {
+    /// `[data, encoding]`
+    data: (String, String),
+}
+
+#[derive(Debug, Deserialize)]
+struct AccountNotificationResult {
+    context: AccountNotificationContext,
+    value: Option<AccountNotificationValue>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccountNotificationParams {
+    subscription: u64,
+    result: AccountNotificationResult,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccountNotification {
+    params: AccountNotificationParams,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubscriptionResponse {
+    id: u64,
+    result: Value,
+}
+
+/// Subscriptions of a single connection, keyed by token mint.
+#[derive(Default)]
+struct SubscriptionState {
+    next_request_id: u64,
+    pending: HashMap<u64, String>,
+    subscriptions: HashMap<u64, String>,
+    mints: HashMap<String, Option<u64>>,
+}
+
+async fn unsubscribe<S>(write: &mut S, state: &mut SubscriptionState, subscription: u64) -> Result<(), SourceError>
+where
+    S: futures::Sink<WsMessage, Error = tokio_tungstenite::tungstenite::Error> + Unpin
+{
+    state.next_request_id += 1;
+    write.send(WsMessage::Text(json!({
+        "jsonrpc": "2.0",
+        "id": state.next_request_id,
+        "method": "accountUnsubscribe",
+        "params": [subscription]
+    }).to_string())).await?;
+    Ok(())
+}
+
+#[derive(Clone)]
+pub struct BondingCurveProcessor {
+    ws_url: String,
+    redis_pool: Arc<Pool>,
+    tracked_sets: watch::Receiver<u64>,
+    tx: UnboundedSender<MpscMessage>,
+}
+
+impl BondingCurveProcessor {
+    /// `tracked_sets` is the shared watcher of the tracked sets, see `watch_tracked_sets`.
+    pub fn new(ws_url: &str, redis_pool: Arc<Pool>, tracked_sets: watch::Receiver<u64>, tx: UnboundedSender<MpscMessage>) -> Self {
+        Self {
+            ws_url: ws_url.to_string(),
+            redis_pool,
+            tracked_sets,
+            tx,
+        }
+    }
+
+    pub async fn start_processor(&self) {
+        loop {
+            if let Err(err) = self.process_connection().await {
+                warn!("Bonding curve subscription failed: {}", err);
+            }
+
+            warn!("Bonding curve stream closed. Will retry in 1 second(s)...");
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+
+    async fn process_connection(&self) -> Result<(), SourceError> {
+        info!("Connecting to: {}", self.ws_url);
+        let (ws, _response) = connect_async(&self.ws_url).await?;
+        info!("WebSocket connection established!");
+
+        let (mut write, mut read) = ws.split();
+        let mut state = SubscriptionState::default();
+        let mut changes = FilterChanges::new(Some(self.tracked_sets.clone()));
+        let mut heartbeat = tokio::time::interval(Duration::from_secs(30));
+
+        self.update_subscriptions(&mut write, &mut state).await?;
+
+        loop {
+            tokio::select! {
+                message = read.next() => match message {
+                    Some(Ok(WsMessage::Text(text))) => {
+                        if let Some(subscription) = self.handle_message(&text, &mut state) {
+                            unsubscribe(&mut write, &mut state, subscription).await?;
+                        }
+                    }
+                    Some(Ok(WsMessage::Close(frame))) => {
+                        warn!("WebSocket connection closed: {:?}", frame);
+                        return Ok(());
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(err)) => return Err(err.into()),
+                    None => return Ok(()),
+                },
+                _ = changes.changed() => self.update_subscriptions(&mut write, &mut state).await?,
+                _ = heartbeat.tick() => write.send(WsMessage::Ping(vec![])).await?,
+            }
+        }
+    }
+
+    /// Subscribes to the bonding curves of newly tracked tokens and cancels the subscriptions
+    /// of tokens that are no longer tracked.
+    async fn update_subscriptions<S>(&self, write: &mut S, state: &mut SubscriptionState) -> Result<(), SourceError>
+    where
+        S: futures::Sink<WsMessage, Error = tokio_tungstenite::tungstenite::Error> + Unpin
+    {
+        let mut conn = self.redis_pool.get().await?;
+        let tracked: HashSet<String> = conn.smembers(TRACKED_TOKEN_ADDRESSES).await?;
+
+        let untracked: Vec<String> = state.mints.keys().filter(|mint| !tracked.contains(*mint)).cloned().collect();
+        let added: Vec<String> = tracked.into_iter().filter(|mint| !state.mints.contains_key(mint)).collect();
+
+        for mint in added {
+            let bonding_curve = match BondingCurve::address(&mint) {
+                Some(bonding_curve) => bonding_curve,
+                None => {
+                    warn!("Tracked token {} is not a valid address, skipping.", mint);
+                    continue;
+                }
+            };
+
+            state.next_request_id += 1;
+            write.send(WsMessage::Text(json!({
+                "jsonrpc": "2.0",
+                "id": state.next_request_id,
+                "method": "accountSubscribe",
+                "params": [
+                    bonding_curve,
+                    { "encoding": "base64", "commitment": "processed" }
+                ]
+            }).to_string())).await?;
+
+            state.pending.insert(state.next_request_id, mint.clone());
+            state.mints.insert(mint, None);
+        }
+
+        for mint in untracked {
+            // Subscriptions still pending are cancelled once they are confirmed, see
+            // `handle_message`.
+            if let Some(Some(subscription)) = state.mints.remove(&mint) {
+                state.subscriptions.remove(&subscription);
+                unsubscribe(write, state, subscription).await?;
+            }
+        }
+
+        info!("Subscribed to {} bonding curve(s).", state.mints.len());
+        Ok(())
+    }
+
+    /// Returns the subscription to cancel when it was confirmed for a token that has been
+    /// untracked in the meantime.
+    fn handle_message(&self, text: &str, state: &mut SubscriptionState) -> Option<u64> {
+        if let Ok(notification) = serde_json::from_str::<AccountNotification>(text) {
+            let mint = state.subscriptions.get(&notification.params.subscription)?.clone();
+            let data = notification.params.result.value?.data.0;
+
+            match BASE64_STANDARD.decode(data) {
+                Ok(data) => self.handle_account_data(&mint, notification.params.result.context.slot, &data),
+                Err(err) => warn!("Invalid bonding curve data for {}: {}", mint, err),
+            }
+        } else if let Ok(response) = serde_json::from_str::<SubscriptionResponse>(text) {
+            let (mint, subscription) = match (state.pending.remove(&response.id), response.result.as_u64()) {
+                (Some(mint), Some(subscription)) => (mint, subscription),
+                _ => return None,
+            };
+
+            // The token was untracked while the subscription was pending.
+            if !state.mints.contains_key(&mint) {
+                return Some(subscription);
+            }
+
+            state.subscriptions.insert(subscription, mint.clone());
+            state.mints.insert(mint, Some(subscription));
+        } else if let Ok(value) = serde_json::from_str::<Value>(text) {
+            if let Some(error) = value.get("error") {
+                warn!("Bonding curve subscription error: {}", error);
+
+                // Forget the failed request so that it is retried on the next change.
+                let request_id = value.get("id").and_then(Value::as_u64);
+                if let Some(mint) = request_id.and_then(|id| state.pending.remove(&id)) {
+                    state.mints.remove(&mint);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Decodes and publishes raw `BondingCurve` account data, e.g. from an `accountSubscribe`
+    /// notification or a geyser account update.
+    pub fn handle_account_data(&self, mint: &str, slot: u64, data: &[u8]) {
+        let bonding_curve = match BondingCurve::from_account_data(data) {
+            Some(bonding_curve) => bonding_curve,
+            None => {
+                warn!("Failed to decode bonding curve account of {}", Paint::black(mint));
+                return;
+            }
+        };
+
+        let message = PumpFunBondingCurveUpdate {
+            token_address: mint.to_string(),
+            bonding_curve: BondingCurve::address(mint).unwrap_or_default(),
+            real_lamport_reserves: bonding_curve.real_sol_reserves,
+            real_token_reserves: bonding_curve.real_token_reserves,
+            virtual_lamport_reserves: bonding_curve.virtual_sol_reserves,
+            virtual_token_reserves: bonding_curve.virtual_token_reserves,
+            token_total_supply: bonding_curve.token_total_supply,
+            complete: bonding_curve.complete,
+            slot,
+            exact: true,
+        };
+
+        self.tx.send(MpscMessage {
+            topic: PUMP_FUN_BONDING_CURVE_UPDATE.to_string(),
+            payload: message.encode_to_vec()
+        }).expect("Failed to send MPSC Message.");
+
+        info!(
+            "Sending {} for {} {}",
+            Paint::magenta("BONDING_CURVE_ACCOUNT_UPDATE"),
+            Paint::cyan("PUMP_FUN_PROGRAM"),
+            Paint::black(mint)
+        );
+    }
+}
@@ -0,0 +1,226 @@
+/// # Commitment Lifecycle Tracking
+/// Every event is published as soon as its transaction is seen at the `processed` commitment,
+/// which means a fork can still drop it. This processor follows each published signature
+/// through `getSignatureStatuses` and publishes a follow-up event once it is `confirmed`, once
+/// it is `finalized`, or when it is `dropped` (it never reached the cluster before its blockhash
+/// could have expired). Downstream consumers can use the latter to roll back bonding curve and
+/// balance updates that never landed. Transactions that failed on chain did land, their
+/// commitment is followed like any other.
+///
+/// With the JSON-RPC emulator enabled, every observed signature is followed so that
+/// `getSignatureStatuses` reports its actual status, without publishing any events for it.
+
+use tokio::sync::mpsc::UnboundedSender;
+use serde::Deserialize;
+use serde_json::json;
+use std::{collections::HashMap, sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex}, time::{Duration, Instant}};
+use tracing::{info, warn};
+use prost::Message;
+use yansi::Paint;
+use chrono::Utc;
+
+use crate::{
+    constants::zmq::{TRANSACTION_CONFIRMED_UPDATE, TRANSACTION_DROPPED_UPDATE, TRANSACTION_FINALIZED_UPDATE},
+    helpers::json_rpc::JsonRpcClient,
+    messaging::MpscMessage
+};
+
+use super::node_state::NodeState;
+
+pub mod commitment {
+    tonic::include_proto!("commitment");
+}
+
+use commitment::{CommitmentStatus, TransactionCommitmentUpdate};
+
+/// Maximum number of signatures accepted by a single `getSignatureStatuses` request.
+const STATUS_BATCH_SIZE: usize = 256;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A blockhash is valid for 150 blocks (~60 seconds), a transaction that has not been seen by
+/// the cluster after this long can no longer land.
+const DROP_AFTER: Duration = Duration::from_secs(90);
+
+/// Maximum number of signatures followed at once. Signatures settle within `DROP_AFTER`, so
+/// this is only reached when far more events are published than usual.
+const MAX_TRACKED_SIGNATURES: usize = 10_000;
+
+#[derive(Debug, Clone)]
+pub struct TrackedSignature {
+    pub slot: u64,
+    pub status: CommitmentStatus,
+    observed_at: Instant,
+    /// Whether events were published for the transaction. Signatures only followed for the
+    /// JSON-RPC emulator do not publish follow-up events.
+    publish: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SignatureStatus {
+    slot: u64,
+    confirmation_status: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SignatureStatusesResult {
+    value: Vec<Option<SignatureStatus>>,
+}
+
+#[derive(Clone)]
+pub struct CommitmentTracker {
+    rpc_client: JsonRpcClient,
+    tracked: Arc<Mutex<HashMap<String, TrackedSignature>>>,
+    /// Set once `MAX_TRACKED_SIGNATURES` is reached, so that the warning is only logged once.
+    saturated: Arc<AtomicBool>,
+    node_state: Option<NodeState>,
+    tx: UnboundedSender<MpscMessage>,
+}
+
+impl CommitmentTracker {
+    /// The commitment of every signature is also recorded in `node_state` when set.
+    pub fn new(rpc_url: &str, node_state: Option<NodeState>, tx: UnboundedSender<MpscMessage>) -> Self {
+        Self {
+            rpc_client: JsonRpcClient::new(rpc_url),
+            tracked: Arc::new(Mutex::new(HashMap::new())),
+            saturated: Arc::new(AtomicBool::new(false)),
+            node_state,
+            tx,
+        }
+    }
+
+    /// Starts following a signature, unless `MAX_TRACKED_SIGNATURES` are followed already.
+    /// Signatures no events were published for only take up half of that, so that they never
+    /// crowd out the ones consumers are waiting on.
+    pub fn track(&self, signature: &str, slot: u64, publish: bool) {
+        let mut tracked = self.tracked.lock().unwrap();
+        let capacity = if publish { MAX_TRACKED_SIGNATURES } else { MAX_TRACKED_SIGNATURES / 2 };
+        if tracked.len() >= capacity && !tracked.contains_key(signature) {
+            if !self.saturated.swap(true, Ordering::Relaxed) {
+                warn!(
+                    "Following {} signatures already, new signatures are ignored until older ones settle.",
+                    MAX_TRACKED_SIGNATURES
+                );
+            }
+            return;
+        }
+
+        let entry = tracked.entry(signature.to_string()).or_insert(TrackedSignature {
+            slot,
+            status: CommitmentStatus::Processed,
+            observed_at: Instant::now(),
+            publish,
+        });
+        entry.publish |= publish;
+    }
+
+    pub async fn start_tracker(&self) {
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+
+            let signatures: Vec<String> = self.tracked.lock().unwrap().keys().cloned().collect();
+            for batch in signatures.chunks(STATUS_BATCH_SIZE) {
+                if let Err(err) = self.poll_batch(batch).await {
+                    warn!("Failed to poll signature statuses: {}", err);
+                }
+            }
+
+            if self.tracked.lock().unwrap().len() < MAX_TRACKED_SIGNATURES {
+                self.saturated.store(false, Ordering::Relaxed);
+            }
+        }
+    }
+
+    async fn poll_batch(&self, signatures: &[String]) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let statuses: SignatureStatusesResult = self.rpc_client
+            .call("getSignatureStatuses", json!([signatures]))
+            .await?;
+
+        let mut tracked = self.tracked.lock().unwrap();
+        for (signature, status) in signatures.iter().zip(statuses.value) {
+            let entry = match tracked.get_mut(signature) {
+                Some(entry) => entry,
+                None => continue,
+            };
+
+            let next = match &status {
+                Some(status) => match status.confirmation_status.as_deref() {
+                    Some("finalized") => CommitmentStatus::Finalized,
+                    Some("confirmed") => CommitmentStatus::Confirmed,
+                    _ => CommitmentStatus::Processed,
+                },
+                // A transaction that was confirmed already landed, it is only unknown to a
+                // lagging node.
+                None if entry.status == CommitmentStatus::Processed && entry.observed_at.elapsed() >= DROP_AFTER => {
+                    CommitmentStatus::Dropped
+                }
+                None => entry.status,
+            };
+
+            // Statuses only ever move forward, a node lagging behind the one polled before can
+            // report an earlier commitment (e.g. processed after confirmed).
+            let next = if next != CommitmentStatus::Dropped && (next as i32) < (entry.status as i32) {
+                entry.status
+            } else {
+                next
+            };
+
+            if let Some(status) = &status {
+                entry.slot = status.slot;
+            }
+
+            // A transaction can skip straight from processed to finalized between two polls, in
+            // which case both follow-up events are still published in order.
+            if entry.publish && entry.status == CommitmentStatus::Processed && next == CommitmentStatus::Finalized {
+                self.publish(signature, entry.slot, CommitmentStatus::Confirmed);
+            }
+
+            if next != entry.status {
+                if let Some(node_state) = &self.node_state {
+                    node_state.set_commitment(signature, entry.slot, next);
+                }
+
+                entry.status = next;
+                if entry.publish {
+                    self.publish(signature, entry.slot, next);
+                }
+            }
+
+            if matches!(next, CommitmentStatus::Finalized | CommitmentStatus::Dropped) {
+                tracked.remove(signature);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn publish(&self, signature: &str, slot: u64, status: CommitmentStatus) {
+        let topic = match status {
+            CommitmentStatus::Confirmed => TRANSACTION_CONFIRMED_UPDATE,
+            CommitmentStatus::Finalized => TRANSACTION_FINALIZED_UPDATE,
+            CommitmentStatus::Dropped => TRANSACTION_DROPPED_UPDATE,
+            CommitmentStatus::Processed => return,
+        };
+
+        let message = TransactionCommitmentUpdate {
+            signature: signature.to_string(),
+            slot,
+            status: status as i32,
+            timestamp: Utc::now().timestamp_millis(),
+        };
+
+        self.tx.send(MpscMessage {
+            topic: topic.to_string(),
+            payload: message.encode_to_vec(),
+        }).expect("Failed to send MPSC Message.");
+
+        if status == CommitmentStatus::Dropped {
+            info!(
+                "Sending {} for {}",
+                Paint::red("TRANSACTION_DROPPED"),
+                Paint::black(signature)
+            );
+        }
+    }
+}
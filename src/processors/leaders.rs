@@ -0,0 +1,404 @@
+/// # Leader Schedule Tracking
+/// Combines the leader schedule of the current and next epoch with the live slot of the
+/// `BlockhashProcessor` to publish the current leader and the leaders following it, along with
+/// their TPU addresses from `getClusterNodes`, so that transactions can be sent to upcoming
+/// leaders directly. An update is published every time the leader changes.
+///
+/// REQUIRES REDIS: TRUE
+/// - Current and upcoming leaders.
+///
+/// REQUIRES ZMQ: TRUE
+/// - Upcoming leader updates.
+
+use tokio::sync::{mpsc::UnboundedSender, watch};
+use std::{collections::HashMap, sync::Arc, time::{Duration, Instant}};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use deadpool_redis::Pool;
+use redis::AsyncCommands;
+use tracing::{info, warn};
+use prost::Message;
+use yansi::Paint;
+use chrono::Utc;
+
+use crate::{
+    constants::{redis::UPCOMING_LEADERS, zmq::UPCOMING_LEADERS_UPDATE},
+    helpers::json_rpc::{JsonRpcClient, JsonRpcError},
+    messaging::MpscMessage
+};
+
+pub mod leader_schedule {
+    tonic::include_proto!("leader_schedule");
+}
+
+use leader_schedule::{LeaderWindow, UpcomingLeadersUpdate};
+
+/// TPU addresses change rarely, but validators do restart on new addresses.
+const CLUSTER_NODES_REFRESH: Duration = Duration::from_secs(300);
+
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Delay before the schedules are fetched again while the schedule of the next epoch is not
+/// available yet.
+const NEXT_SCHEDULE_RETRY: Duration = Duration::from_secs(60);
+
+/// Lifetime of the `upcoming_leaders` key, so that consumers do not keep targeting leaders
+/// whose slots have long passed when the slot clock stalls.
+const UPCOMING_LEADERS_TTL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EpochInfo {
+    absolute_slot: u64,
+    epoch: u64,
+    slot_index: u64,
+    slots_in_epoch: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ClusterNode {
+    pubkey: String,
+    tpu: Option<String>,
+    tpu_quic: Option<String>,
+}
+
+/// The leader of every slot of an epoch. Identities are stored once, as an epoch has hundreds
+/// of thousands of slots but only a couple thousand leaders.
+struct EpochSchedule {
+    epoch: u64,
+    first_slot: u64,
+    identities: Vec<String>,
+    slot_leaders: Vec<u32>,
+}
+
+impl EpochSchedule {
+    /// `schedule` maps every leader identity to the slot indexes, relative to the first slot of
+    /// the epoch, it is the leader of.
+    fn new(epoch: u64, first_slot: u64, slots_in_epoch: u64, schedule: HashMap<String, Vec<usize>>) -> Self {
+        let mut identities = Vec::with_capacity(schedule.len());
+        let mut slot_leaders = vec![u32::MAX; slots_in_epoch as usize];
+
+        for (identity, slot_indexes) in schedule {
+            let leader = identities.len() as u32;
+            identities.push(identity);
+
+            for slot_index in slot_indexes {
+                if let Some(slot_leader) = slot_leaders.get_mut(slot_index) {
+                    *slot_leader = leader;
+                }
+            }
+        }
+
+        Self {
+            epoch,
+            first_slot,
+            identities,
+            slot_leaders,
+        }
+    }
+
+    fn leader(&self, slot: u64) -> Option<&str> {
+        let slot_index = slot.checked_sub(self.first_slot)? as usize;
+        let leader = *self.slot_leaders.get(slot_index)?;
+        self.identities.get(leader as usize).map(String::as_str)
+    }
+}
+
+/// A leader identity and the first and last slot of its window.
+type Window<'a> = (&'a str, u64, u64);
+
+#[derive(Clone)]
+pub struct LeaderScheduleProcessor {
+    rpc_client: JsonRpcClient,
+    redis_pool: Arc<Pool>,
+    slots: watch::Receiver<u64>,
+    lookahead: usize,
+    tx: UnboundedSender<MpscMessage>,
+}
+
+impl LeaderScheduleProcessor {
+    /// `slots` is the live slot clock, `lookahead` the number of leaders published after the
+    /// current one.
+    pub fn new(
+        rpc_url: &str,
+        redis_pool: Arc<Pool>,
+        slots: watch::Receiver<u64>,
+        lookahead: usize,
+        tx: UnboundedSender<MpscMessage>
+    ) -> Self {
+        Self {
+            rpc_client: JsonRpcClient::new(rpc_url),
+            redis_pool,
+            slots,
+            lookahead,
+            tx,
+        }
+    }
+
+    pub async fn start_processor(&self) {
+        let mut slots = self.slots.clone();
+        let mut schedules: Vec<EpochSchedule> = Vec::new();
+        let mut nodes: HashMap<String, ClusterNode> = HashMap::new();
+        let mut next_nodes_refresh = Instant::now();
+        let mut next_schedules_fetch = Instant::now();
+        let mut last_window: Option<(u64, String)> = None;
+
+        while slots.changed().await.is_ok() {
+            let slot = *slots.borrow_and_update();
+
+            // The schedules are fetched again as soon as the slot enters the last known epoch,
+            // so that the schedule of the following epoch is known before it starts.
+            let known = schedules.iter().any(|schedule| schedule.leader(slot).is_some());
+            let in_last_epoch = schedules.last().map_or(true, |last| slot >= last.first_slot);
+            if !known || (in_last_epoch && Instant::now() >= next_schedules_fetch) {
+                match self.fetch_schedules().await {
+                    Ok(fetched) => {
+                        schedules = fetched;
+                        next_schedules_fetch = Instant::now() + NEXT_SCHEDULE_RETRY;
+                    }
+                    Err(err) if !known => {
+                        warn!("Failed to fetch leader schedule: {}. Retrying in 1 second(s)...", err);
+                        tokio::time::sleep(RETRY_DELAY).await;
+                        continue;
+                    }
+                    Err(err) => {
+                        warn!("Failed to fetch the next leader schedule: {}", err);
+                        next_schedules_fetch = Instant::now() + RETRY_DELAY;
+                    }
+                }
+            }
+
+            if Instant::now() >= next_nodes_refresh {
+                match self.fetch_cluster_nodes().await {
+                    Ok(fetched) => {
+                        nodes = fetched;
+                        next_nodes_refresh = Instant::now() + CLUSTER_NODES_REFRESH;
+                    }
+                    Err(err) => {
+                        warn!("Failed to fetch cluster nodes: {}", err);
+                        next_nodes_refresh = Instant::now() + RETRY_DELAY;
+                    }
+                }
+            }
+
+            let windows = leader_windows(&schedules, slot, self.lookahead + 1);
+            let (identity, first_slot, _) = match windows.first() {
+                Some(current) => *current,
+                None => continue,
+            };
+
+            let window = (first_slot, identity.to_string());
+            if last_window.as_ref() == Some(&window) {
+                continue;
+            }
+            last_window = Some(window);
+
+            let epoch = schedules.iter()
+                .find(|schedule| schedule.leader(slot).is_some())
+                .map_or(0, |schedule| schedule.epoch);
+            self.publish(slot, epoch, &windows, &nodes).await;
+        }
+
+        warn!("Slot clock closed, stopped tracking the leader schedule.");
+    }
+
+    /// Fetches the schedule of the current epoch, and of the next one when it is known already.
+    async fn fetch_schedules(&self) -> Result<Vec<EpochSchedule>, JsonRpcError> {
+        let epoch_info: EpochInfo = self.rpc_client.call(
+            "getEpochInfo",
+            json!([{ "commitment": "confirmed" }])
+        ).await?;
+
+        let first_slot = epoch_info.absolute_slot - epoch_info.slot_index;
+        let epochs = [
+            (epoch_info.epoch, first_slot),
+            (epoch_info.epoch + 1, first_slot + epoch_info.slots_in_epoch),
+        ];
+
+        let mut schedules = Vec::new();
+        for (epoch, first_slot) in epochs {
+            let schedule: Option<HashMap<String, Vec<usize>>> = self.rpc_client.call(
+                "getLeaderSchedule",
+                json!([first_slot, { "commitment": "confirmed" }])
+            ).await?;
+
+            match schedule {
+                Some(schedule) => {
+                    info!("Fetched leader schedule of epoch {} ({} leaders)", Paint::black(epoch), schedule.len());
+                    schedules.push(EpochSchedule::new(epoch, first_slot, epoch_info.slots_in_epoch, schedule));
+                }
+                None => break,
+            }
+        }
+
+        if schedules.is_empty() {
+            return Err(format!("No leader schedule available for epoch {}", epoch_info.epoch).into());
+        }
+
+        Ok(schedules)
+    }
+
+    async fn fetch_cluster_nodes(&self) -> Result<HashMap<String, ClusterNode>, JsonRpcError> {
+        let nodes: Vec<ClusterNode> = self.rpc_client.call("getClusterNodes", json!([])).await?;
+
+        Ok(nodes.into_iter().map(|node| (node.pubkey.clone(), node)).collect())
+    }
+
+    async fn publish(&self, slot: u64, epoch: u64, windows: &[Window<'_>], nodes: &HashMap<String, ClusterNode>) {
+        let mut windows = windows.iter().map(|(identity, first_slot, last_slot)| {
+            let node = nodes.get(*identity);
+            LeaderWindow {
+                identity: identity.to_string(),
+                tpu: node.and_then(|node| node.tpu.clone()).unwrap_or_default(),
+                tpu_quic: node.and_then(|node| node.tpu_quic.clone()).unwrap_or_default(),
+                first_slot: *first_slot,
+                last_slot: *last_slot,
+            }
+        });
+
+        let mpsc_message = UpcomingLeadersUpdate {
+            slot,
+            epoch,
+            current: windows.next(),
+            upcoming: windows.collect(),
+            timestamp: Utc::now().timestamp_millis(),
+        };
+
+        let value = json!({
+            "slot": mpsc_message.slot,
+            "epoch": mpsc_message.epoch,
+            "current": mpsc_message.current.as_ref().map(window_json),
+            "upcoming": mpsc_message.upcoming.iter().map(window_json).collect::<Vec<Value>>(),
+            "timestamp": mpsc_message.timestamp,
+        });
+
+        match self.redis_pool.get().await {
+            Ok(mut conn) => {
+                let result = conn.pset_ex::<&str, String, ()>(
+                    UPCOMING_LEADERS,
+                    value.to_string(),
+                    UPCOMING_LEADERS_TTL.as_millis() as u64
+                ).await;
+                if let Err(err) = result {
+                    warn!("Failed to store upcoming leaders: {}", err);
+                }
+            }
+            Err(err) => warn!("Failed to store upcoming leaders: {}", err),
+        }
+
+        self.tx.send(MpscMessage {
+            topic: UPCOMING_LEADERS_UPDATE.to_string(),
+            payload: mpsc_message.encode_to_vec(),
+        }).expect("Failed to send MPSC Message.");
+
+        info!(
+            "Sending {} for {}",
+            Paint::magenta("UPCOMING_LEADERS_UPDATE"),
+            Paint::cyan(mpsc_message.current.map(|current| current.identity).unwrap_or_default())
+        );
+    }
+}
+
+fn window_json(window: &LeaderWindow) -> Value {
+    json!({
+        "identity": window.identity,
+        "tpu": window.tpu,
+        "tpuQuic": window.tpu_quic,
+        "firstSlot": window.first_slot,
+        "lastSlot": window.last_slot,
+    })
+}
+
+fn leader_at(schedules: &[EpochSchedule], slot: u64) -> Option<&str> {
+    schedules.iter().find_map(|schedule| schedule.leader(slot))
+}
+
+/// The window of the leader of `slot` followed by the next windows, up to `count` in total or
+/// until the end of the known schedules. Consecutive slots of the same leader form one window.
+fn leader_windows(schedules: &[EpochSchedule], slot: u64, count: usize) -> Vec<Window<'_>> {
+    let current = match leader_at(schedules, slot) {
+        Some(current) => current,
+        None => return Vec::new(),
+    };
+
+    let mut first_slot = slot;
+    while first_slot > 0 && leader_at(schedules, first_slot - 1) == Some(current) {
+        first_slot -= 1;
+    }
+
+    let mut windows = vec![(current, first_slot, slot)];
+    let mut next_slot = slot + 1;
+    while let Some(leader) = leader_at(schedules, next_slot) {
+        let last = windows.last_mut().unwrap();
+        if leader == last.0 {
+            last.2 = next_slot;
+        } else if windows.len() < count {
+            windows.push((leader, next_slot, next_slot));
+        } else {
+            break;
+        }
+
+        next_slot += 1;
+    }
+
+    windows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An epoch of `leaders.len() * 4` slots, each leader holding four consecutive slots.
+    fn epoch(epoch: u64, first_slot: u64, leaders: &[&str]) -> EpochSchedule {
+        let mut schedule: HashMap<String, Vec<usize>> = HashMap::new();
+        for (index, leader) in leaders.iter().enumerate() {
+            schedule.entry(leader.to_string()).or_default().extend(index * 4..index * 4 + 4);
+        }
+        EpochSchedule::new(epoch, first_slot, leaders.len() as u64 * 4, schedule)
+    }
+
+    #[test]
+    fn resolves_the_leader_of_a_slot() {
+        let schedule = epoch(10, 100, &["a", "b"]);
+        assert_eq!(schedule.leader(99), None);
+        assert_eq!(schedule.leader(100), Some("a"));
+        assert_eq!(schedule.leader(103), Some("a"));
+        assert_eq!(schedule.leader(104), Some("b"));
+        assert_eq!(schedule.leader(107), Some("b"));
+        assert_eq!(schedule.leader(108), None);
+    }
+
+    #[test]
+    fn slots_missing_from_the_schedule_have_no_leader() {
+        let schedule = EpochSchedule::new(0, 0, 4, HashMap::from([("a".to_string(), vec![0, 1, 9])]));
+        assert_eq!(schedule.leader(1), Some("a"));
+        assert_eq!(schedule.leader(2), None);
+        assert_eq!(schedule.leader(9), None);
+    }
+
+    #[test]
+    fn merges_consecutive_slots_into_windows() {
+        let schedules = vec![epoch(10, 100, &["a", "b", "b", "c"])];
+
+        assert_eq!(leader_windows(&schedules, 102, 3), vec![("a", 100, 103), ("b", 104, 111), ("c", 112, 115)]);
+        assert_eq!(leader_windows(&schedules, 105, 1), vec![("b", 104, 111)]);
+        assert_eq!(leader_windows(&schedules, 99, 3), vec![]);
+    }
+
+    #[test]
+    fn windows_span_the_epoch_boundary() {
+        let schedules = vec![
+            epoch(10, 100, &["a", "b"]),
+            epoch(11, 108, &["b", "c"]),
+        ];
+
+        // The window of `b` continues into the next epoch, from either side of the boundary.
+        assert_eq!(leader_windows(&schedules, 105, 2), vec![("b", 104, 111), ("c", 112, 115)]);
+        assert_eq!(leader_windows(&schedules, 109, 2), vec![("b", 104, 111), ("c", 112, 115)]);
+
+        // Windows end with the last known schedule.
+        assert_eq!(leader_windows(&schedules, 113, 5), vec![("c", 112, 115)]);
+        assert_eq!(leader_windows(&schedules[..1], 105, 5), vec![("b", 104, 107)]);
+    }
+}
@@ -1,2 +1,10 @@
 pub mod transactions;
-pub mod blockhashes;
\ No newline at end of file
+pub mod blockhashes;
+pub mod blockhash_service;
+pub mod commitments;
+pub mod bonding_curves;
+pub mod leaders;
+pub mod priority_fees;
+pub mod priority_fee_service;
+pub mod node_state;
+pub mod rpc_emulator;
\ No newline at end of file
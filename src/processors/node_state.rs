@@ -0,0 +1,142 @@
+/// # Node State
+/// The account and signature state observed by the `TransactionProcessor`, kept in memory for
+/// the JSON-RPC emulator. Balances are only known for tracked addresses, and only once a
+/// transaction touching them has been observed since startup.
+
+use solana_sdk::transaction::TransactionError;
+use std::{collections::{HashMap, VecDeque}, sync::{Arc, Mutex}};
+
+use super::commitments::commitment::CommitmentStatus;
+
+/// Number of signatures whose status is remembered, oldest are forgotten first.
+const SIGNATURES_CAPACITY: usize = 50_000;
+
+#[derive(Debug, Clone)]
+pub struct TokenAccountState {
+    pub address: String,
+    pub mint: String,
+    pub owner: String,
+    pub program_id: String,
+    pub amount: u64,
+    pub decimals: u8,
+    pub lamports: u64,
+    pub slot: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct SignatureState {
+    pub slot: u64,
+    pub err: Option<TransactionError>,
+    pub status: CommitmentStatus,
+}
+
+#[derive(Default)]
+struct NodeStateInner {
+    /// Lamports of every tracked address, along with the slot they were observed in.
+    balances: HashMap<String, (u64, u64)>,
+    /// Token accounts of tracked owners, keyed by token account address.
+    token_accounts: HashMap<String, TokenAccountState>,
+    signatures: HashMap<String, SignatureState>,
+    signature_order: VecDeque<String>,
+}
+
+#[derive(Clone, Default)]
+pub struct NodeState {
+    inner: Arc<Mutex<NodeStateInner>>,
+}
+
+impl NodeState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Transactions are not always observed in slot order, an older balance never replaces a
+    /// newer one.
+    pub fn record_balance(&self, address: &str, slot: u64, lamports: u64) {
+        let mut inner = self.inner.lock().unwrap();
+        let balance = inner.balances.entry(address.to_string()).or_insert((slot, lamports));
+        if slot >= balance.0 {
+            *balance = (slot, lamports);
+        }
+    }
+
+    /// The lamports of `address` and the slot they were observed in.
+    pub fn balance(&self, address: &str) -> Option<(u64, u64)> {
+        self.inner.lock().unwrap().balances.get(address).copied()
+    }
+
+    pub fn record_token_account(&self, token_account: TokenAccountState) {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.token_accounts.get(&token_account.address) {
+            Some(existing) if existing.slot > token_account.slot => {}
+            _ => {
+                inner.token_accounts.insert(token_account.address.clone(), token_account);
+            }
+        }
+    }
+
+    /// Forgets a closed token account, unless it was observed again in a later slot.
+    pub fn remove_token_account(&self, address: &str, slot: u64) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.token_accounts.get(address).is_some_and(|existing| existing.slot <= slot) {
+            inner.token_accounts.remove(address);
+        }
+    }
+
+    pub fn token_accounts_by_owner(&self, owner: &str) -> Vec<TokenAccountState> {
+        self.inner.lock().unwrap().token_accounts
+            .values()
+            .filter(|token_account| token_account.owner == owner)
+            .cloned()
+            .collect()
+    }
+
+    /// Records a signature as it is observed by the transaction source, at the commitment the
+    /// source streams at.
+    pub fn record_signature(&self, signature: &str, slot: u64, err: Option<TransactionError>, status: CommitmentStatus) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.signatures.contains_key(signature) {
+            return;
+        }
+
+        inner.signatures.insert(signature.to_string(), SignatureState {
+            slot,
+            err,
+            status,
+        });
+        inner.signature_order.push_back(signature.to_string());
+
+        if inner.signature_order.len() > SIGNATURES_CAPACITY {
+            if let Some(oldest) = inner.signature_order.pop_front() {
+                inner.signatures.remove(&oldest);
+            }
+        }
+    }
+
+    /// Follows the commitment of an observed signature. A dropped transaction never landed, so
+    /// it is forgotten. Statuses never move backwards, a signature observed at `confirmed`
+    /// stays confirmed.
+    pub fn set_commitment(&self, signature: &str, slot: u64, status: CommitmentStatus) {
+        let mut inner = self.inner.lock().unwrap();
+        let state = match inner.signatures.get_mut(signature) {
+            Some(state) => state,
+            None => return,
+        };
+
+        match status {
+            CommitmentStatus::Dropped if state.status == CommitmentStatus::Processed => {
+                inner.signatures.remove(signature);
+            }
+            CommitmentStatus::Dropped => {}
+            status if (status as i32) <= (state.status as i32) => {}
+            status => {
+                state.slot = slot;
+                state.status = status;
+            }
+        }
+    }
+
+    pub fn signature_status(&self, signature: &str) -> Option<SignatureState> {
+        self.inner.lock().unwrap().signatures.get(signature).cloned()
+    }
+}
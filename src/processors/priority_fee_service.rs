@@ -0,0 +1,62 @@
+/// # Priority Fee gRPC Service
+/// Serves the recommendations of the `PriorityFeeEstimator` to transaction senders, for the
+/// writable accounts of the transaction they are about to send.
+
+use tonic::{Request, Response, Status};
+
+use super::priority_fees::{self, PriorityFeeEstimator};
+
+pub mod priority_fee {
+    tonic::include_proto!("priority_fee");
+}
+
+use priority_fee::{
+    priority_fee_service_server::PriorityFeeService,
+    FeeLevels,
+    GetPriorityFeeEstimateRequest,
+    PriorityFeeEstimate
+};
+
+pub use priority_fee::priority_fee_service_server::PriorityFeeServiceServer;
+
+pub struct PriorityFeeGrpcService {
+    estimator: PriorityFeeEstimator,
+}
+
+impl PriorityFeeGrpcService {
+    pub fn new(estimator: PriorityFeeEstimator) -> Self {
+        Self { estimator }
+    }
+}
+
+fn to_message(levels: priority_fees::FeeLevels) -> FeeLevels {
+    FeeLevels {
+        samples: levels.samples as u32,
+        min: levels.min,
+        p25: levels.p25,
+        p50: levels.p50,
+        p75: levels.p75,
+        p90: levels.p90,
+        p99: levels.p99,
+        max: levels.max,
+        median_compute_units: levels.median_compute_units,
+    }
+}
+
+#[tonic::async_trait]
+impl PriorityFeeService for PriorityFeeGrpcService {
+    async fn get_priority_fee_estimate(
+        &self,
+        request: Request<GetPriorityFeeEstimateRequest>
+    ) -> Result<Response<PriorityFeeEstimate>, Status> {
+        let estimate = self.estimator.estimate(&request.into_inner().accounts);
+        let global = estimate.global
+            .ok_or_else(|| Status::unavailable("No transaction has been observed yet"))?;
+
+        Ok(Response::new(PriorityFeeEstimate {
+            slot: estimate.slot,
+            global: Some(to_message(global)),
+            accounts: estimate.accounts.map(to_message),
+        }))
+    }
+}
@@ -0,0 +1,489 @@
+/// # Priority Fee Estimation
+/// Derives priority fee recommendations from the transactions flowing through the
+/// `TransactionProcessor`. The compute unit price set through the ComputeBudget program is
+/// recorded per slot, for every transaction and for each account it writes to, so that fees
+/// can be recommended both globally and for contended accounts such as a popular pump.fun
+/// bonding curve. Transactions without a compute unit price count as paying 0.
+///
+/// REQUIRES REDIS: TRUE
+/// - Global fee recommendations, and those of the busiest writable accounts.
+
+use solana_transaction_status::{
+    option_serializer::OptionSerializer,
+    UiInstruction,
+    UiParsedInstruction,
+    UiParsedMessage,
+    UiTransactionStatusMeta
+};
+use std::{collections::{HashMap, VecDeque}, sync::{Arc, Mutex}, time::Duration};
+use deadpool_redis::Pool;
+use tracing::warn;
+use chrono::Utc;
+
+use crate::constants::redis::PRIORITY_FEE_ESTIMATE;
+
+const COMPUTE_BUDGET_PROGRAM_ID: &str = "ComputeBudget111111111111111111111111111111";
+
+/// Discriminator of the `SetComputeUnitPrice` ComputeBudget instruction, followed by the price
+/// in micro-lamports per compute unit as a little endian u64.
+const SET_COMPUTE_UNIT_PRICE: u8 = 3;
+
+/// Number of slots (~20 seconds) the recommendations are computed over.
+const WINDOW_SLOTS: u64 = 50;
+
+const PUBLISH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Lifetime of the Redis keys, so that recommendations disappear rather than go stale when
+/// the transaction stream stops.
+const ESTIMATE_TTL: Duration = Duration::from_secs(10);
+
+/// Writable accounts with fewer samples in the window are too quiet to be worth a Redis key.
+const MIN_ACCOUNT_SAMPLES: usize = 10;
+
+/// Maximum number of per-account keys written on every publish, busiest accounts first.
+const MAX_PUBLISHED_ACCOUNTS: usize = 100;
+
+#[derive(Debug, Clone, Copy)]
+struct FeeSample {
+    /// Micro-lamports per compute unit.
+    price: u64,
+    compute_units: u64,
+}
+
+#[derive(Default)]
+struct SlotFees {
+    slot: u64,
+    samples: Vec<FeeSample>,
+    accounts: HashMap<String, Vec<FeeSample>>,
+}
+
+/// Compute unit prices in micro-lamports of the transactions observed within the window.
+#[derive(Debug, Clone, Default)]
+pub struct FeeLevels {
+    pub samples: usize,
+    pub min: u64,
+    pub p25: u64,
+    pub p50: u64,
+    pub p75: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub max: u64,
+    /// Median compute units consumed, to estimate the total priority fee of a transaction.
+    pub median_compute_units: u64,
+}
+
+impl FeeLevels {
+    fn from_samples(samples: &[FeeSample]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+
+        let mut prices: Vec<u64> = samples.iter().map(|sample| sample.price).collect();
+        let mut compute_units: Vec<u64> = samples.iter().map(|sample| sample.compute_units).collect();
+        prices.sort_unstable();
+        compute_units.sort_unstable();
+
+        Some(Self {
+            samples: samples.len(),
+            min: prices[0],
+            p25: percentile(&prices, 25.0),
+            p50: percentile(&prices, 50.0),
+            p75: percentile(&prices, 75.0),
+            p90: percentile(&prices, 90.0),
+            p99: percentile(&prices, 99.0),
+            max: prices[prices.len() - 1],
+            median_compute_units: percentile(&compute_units, 50.0),
+        })
+    }
+
+    /// The higher of both levels at every percentile, so that a transaction writing to several
+    /// accounts pays enough for the most contended one.
+    fn max_of(self, other: FeeLevels) -> FeeLevels {
+        FeeLevels {
+            samples: self.samples + other.samples,
+            min: self.min.max(other.min),
+            p25: self.p25.max(other.p25),
+            p50: self.p50.max(other.p50),
+            p75: self.p75.max(other.p75),
+            p90: self.p90.max(other.p90),
+            p99: self.p99.max(other.p99),
+            max: self.max.max(other.max),
+            median_compute_units: self.median_compute_units.max(other.median_compute_units),
+        }
+    }
+
+    fn fields(&self, slot: u64, timestamp: i64) -> Vec<(&'static str, String)> {
+        vec![
+            ("slot", slot.to_string()),
+            ("samples", self.samples.to_string()),
+            ("min", self.min.to_string()),
+            ("p25", self.p25.to_string()),
+            ("p50", self.p50.to_string()),
+            ("p75", self.p75.to_string()),
+            ("p90", self.p90.to_string()),
+            ("p99", self.p99.to_string()),
+            ("max", self.max.to_string()),
+            ("median_compute_units", self.median_compute_units.to_string()),
+            ("timestamp", timestamp.to_string()),
+        ]
+    }
+}
+
+/// Nearest-rank percentile of sorted, non-empty values.
+fn percentile(sorted: &[u64], p: f64) -> u64 {
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PriorityFeeEstimate {
+    /// Most recent slot a transaction was observed in.
+    pub slot: u64,
+    pub global: Option<FeeLevels>,
+    /// Only set when accounts were requested and at least one of them was written to.
+    pub accounts: Option<FeeLevels>,
+}
+
+#[derive(Clone)]
+pub struct PriorityFeeEstimator {
+    slots: Arc<Mutex<VecDeque<SlotFees>>>,
+    redis_pool: Arc<Pool>,
+}
+
+impl PriorityFeeEstimator {
+    pub fn new(redis_pool: Arc<Pool>) -> Self {
+        Self {
+            slots: Arc::new(Mutex::new(VecDeque::new())),
+            redis_pool,
+        }
+    }
+
+    /// Records the compute unit price of a transaction, for the transaction as a whole and for
+    /// every account it writes to.
+    pub fn record(&self, slot: u64, message: &UiParsedMessage, meta: &UiTransactionStatusMeta) {
+        let sample = FeeSample {
+            price: compute_unit_price(message).unwrap_or(0),
+            compute_units: match meta.compute_units_consumed {
+                OptionSerializer::Some(compute_units) => compute_units,
+                _ => 0,
+            },
+        };
+
+        let mut slots = self.slots.lock().unwrap();
+        let latest_slot = slots.back().map_or(slot, |latest| latest.slot.max(slot));
+        if slot + WINDOW_SLOTS <= latest_slot {
+            return;
+        }
+
+        // Slots are mostly observed in order, the position is only searched for late ones.
+        let position = slots.iter().rposition(|slot_fees| slot_fees.slot <= slot);
+        let slot_fees = match position {
+            Some(position) if slots[position].slot == slot => &mut slots[position],
+            _ => {
+                let insert_at = position.map_or(0, |position| position + 1);
+                slots.insert(insert_at, SlotFees { slot, ..SlotFees::default() });
+                &mut slots[insert_at]
+            }
+        };
+
+        slot_fees.samples.push(sample);
+        for account in message.account_keys.iter().filter(|account| account.writable) {
+            slot_fees.accounts.entry(account.pubkey.clone()).or_default().push(sample);
+        }
+
+        while slots.front().is_some_and(|oldest| oldest.slot + WINDOW_SLOTS <= latest_slot) {
+            slots.pop_front();
+        }
+    }
+
+    /// Recommendations over the window, globally and for the given writable accounts.
+    pub fn estimate(&self, accounts: &[String]) -> PriorityFeeEstimate {
+        let slots = self.slots.lock().unwrap();
+
+        let global: Vec<FeeSample> = slots.iter().flat_map(|slot_fees| slot_fees.samples.iter().copied()).collect();
+        let accounts = accounts.iter()
+            .filter_map(|account| {
+                let samples: Vec<FeeSample> = slots.iter()
+                    .filter_map(|slot_fees| slot_fees.accounts.get(account))
+                    .flatten()
+                    .copied()
+                    .collect();
+                FeeLevels::from_samples(&samples)
+            })
+            .reduce(FeeLevels::max_of);
+
+        PriorityFeeEstimate {
+            slot: slots.back().map_or(0, |latest| latest.slot),
+            global: FeeLevels::from_samples(&global),
+            accounts,
+        }
+    }
+
+    /// The busiest writable accounts of the window, along with their recommendations.
+    fn busiest_accounts(&self) -> Vec<(String, FeeLevels)> {
+        let slots = self.slots.lock().unwrap();
+
+        let mut samples: HashMap<&str, Vec<FeeSample>> = HashMap::new();
+        for slot_fees in slots.iter() {
+            for (account, account_samples) in &slot_fees.accounts {
+                samples.entry(account.as_str()).or_default().extend_from_slice(account_samples);
+            }
+        }
+
+        let mut busiest: Vec<(&str, Vec<FeeSample>)> = samples.into_iter()
+            .filter(|(_, samples)| samples.len() >= MIN_ACCOUNT_SAMPLES)
+            .collect();
+        busiest.sort_by(|a, b| b.1.len().cmp(&a.1.len()));
+        busiest.truncate(MAX_PUBLISHED_ACCOUNTS);
+
+        busiest.into_iter()
+            .filter_map(|(account, samples)| FeeLevels::from_samples(&samples).map(|levels| (account.to_string(), levels)))
+            .collect()
+    }
+
+    /// Stores the recommendations under `priority_fee_estimate`, and those of the busiest
+    /// writable accounts under `priority_fee_estimate:{account}`, every second.
+    pub async fn start_publisher(&self) {
+        loop {
+            tokio::time::sleep(PUBLISH_INTERVAL).await;
+
+            if let Err(err) = self.publish().await {
+                warn!("Failed to store priority fee estimates: {}", err);
+            }
+        }
+    }
+
+    async fn publish(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let estimate = self.estimate(&[]);
+        let global = match estimate.global {
+            Some(global) => global,
+            None => return Ok(()),
+        };
+
+        let timestamp = Utc::now().timestamp_millis();
+        let ttl_ms = ESTIMATE_TTL.as_millis() as i64;
+
+        let mut pipe = redis::pipe();
+        pipe.hset_multiple(PRIORITY_FEE_ESTIMATE, &global.fields(estimate.slot, timestamp)).ignore()
+            .pexpire(PRIORITY_FEE_ESTIMATE, ttl_ms).ignore();
+
+        for (account, levels) in self.busiest_accounts() {
+            let key = format!("{}:{}", PRIORITY_FEE_ESTIMATE, account);
+            pipe.hset_multiple(&key, &levels.fields(estimate.slot, timestamp)).ignore()
+                .pexpire(&key, ttl_ms).ignore();
+        }
+
+        let mut conn = self.redis_pool.get().await?;
+        pipe.query_async::<()>(&mut *conn).await?;
+
+        Ok(())
+    }
+}
+
+/// The price set by the `SetComputeUnitPrice` instruction of the transaction, if any.
+/// ComputeBudget instructions are only effective as top-level instructions.
+fn compute_unit_price(message: &UiParsedMessage) -> Option<u64> {
+    message.instructions.iter().find_map(|instruction| {
+        let (program_id, data) = match instruction {
+            UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(instruction)) => {
+                (instruction.program_id.as_str(), instruction.data.as_str())
+            }
+            UiInstruction::Compiled(instruction) => (
+                message.account_keys.get(instruction.program_id_index as usize)?.pubkey.as_str(),
+                instruction.data.as_str()
+            ),
+            UiInstruction::Parsed(UiParsedInstruction::Parsed(_)) => return None,
+        };
+
+        if program_id != COMPUTE_BUDGET_PROGRAM_ID {
+            return None;
+        }
+
+        let data = bs58::decode(data).into_vec().ok()?;
+        match data.split_first() {
+            Some((&SET_COMPUTE_UNIT_PRICE, price)) => Some(u64::from_le_bytes(price.get(..8)?.try_into().ok()?)),
+            _ => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use deadpool_redis::{Config, Runtime};
+    use solana_transaction_status::{parse_accounts::ParsedAccount, UiCompiledInstruction, UiPartiallyDecodedInstruction};
+
+    const BONDING_CURVE: &str = "BondingCurve1111111111111111111111111111111";
+
+    fn estimator() -> PriorityFeeEstimator {
+        // Nothing is stored, the estimator only connects to Redis when publishing.
+        let redis_pool = Config::from_url("redis://127.0.0.1:1").create_pool(Some(Runtime::Tokio1)).unwrap();
+        PriorityFeeEstimator::new(Arc::new(redis_pool))
+    }
+
+    fn account(pubkey: &str, writable: bool) -> ParsedAccount {
+        ParsedAccount { pubkey: pubkey.to_string(), writable, signer: false, source: None }
+    }
+
+    fn compute_budget_data(discriminator: u8, value: &[u8]) -> String {
+        bs58::encode([&[discriminator], value].concat()).into_string()
+    }
+
+    fn set_price(price: u64) -> UiInstruction {
+        UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(UiPartiallyDecodedInstruction {
+            program_id: COMPUTE_BUDGET_PROGRAM_ID.to_string(),
+            accounts: vec![],
+            data: compute_budget_data(SET_COMPUTE_UNIT_PRICE, &price.to_le_bytes()),
+            stack_height: None,
+        }))
+    }
+
+    fn message(instructions: Vec<UiInstruction>, writable: &[&str]) -> UiParsedMessage {
+        let mut account_keys = vec![account("Payer111111111111111111111111111111111111111", true)];
+        account_keys.extend(writable.iter().map(|pubkey| account(pubkey, true)));
+        account_keys.push(account(COMPUTE_BUDGET_PROGRAM_ID, false));
+
+        UiParsedMessage {
+            account_keys,
+            recent_blockhash: String::new(),
+            instructions,
+            address_table_lookups: None,
+        }
+    }
+
+    fn meta(compute_units: u64) -> UiTransactionStatusMeta {
+        serde_json::from_value(serde_json::json!({
+            "err": null,
+            "status": { "Ok": null },
+            "fee": 5000,
+            "preBalances": [],
+            "postBalances": [],
+            "computeUnitsConsumed": compute_units,
+        })).unwrap()
+    }
+
+    #[test]
+    fn decodes_set_compute_unit_price() {
+        assert_eq!(compute_unit_price(&message(vec![set_price(12_345)], &[])), Some(12_345));
+        assert_eq!(compute_unit_price(&message(vec![set_price(u64::MAX)], &[])), Some(u64::MAX));
+        assert_eq!(compute_unit_price(&message(vec![], &[])), None);
+
+        // A compiled instruction refers to the program through the account keys.
+        let compiled = message(vec![UiInstruction::Compiled(UiCompiledInstruction {
+            program_id_index: 1,
+            accounts: vec![],
+            data: compute_budget_data(SET_COMPUTE_UNIT_PRICE, &7u64.to_le_bytes()),
+            stack_height: None,
+        })], &[]);
+        assert_eq!(compute_unit_price(&compiled), Some(7));
+
+        // SetComputeUnitLimit comes first and is skipped.
+        let limit = UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(UiPartiallyDecodedInstruction {
+            program_id: COMPUTE_BUDGET_PROGRAM_ID.to_string(),
+            accounts: vec![],
+            data: compute_budget_data(2, &200_000u32.to_le_bytes()),
+            stack_height: None,
+        }));
+        assert_eq!(compute_unit_price(&message(vec![limit.clone()], &[])), None);
+        assert_eq!(compute_unit_price(&message(vec![limit, set_price(99)], &[])), Some(99));
+
+        let truncated = UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(UiPartiallyDecodedInstruction {
+            program_id: COMPUTE_BUDGET_PROGRAM_ID.to_string(),
+            accounts: vec![],
+            data: compute_budget_data(SET_COMPUTE_UNIT_PRICE, &[1, 2, 3]),
+            stack_height: None,
+        }));
+        assert_eq!(compute_unit_price(&message(vec![truncated], &[])), None);
+
+        let other_program = UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(UiPartiallyDecodedInstruction {
+            program_id: "11111111111111111111111111111111".to_string(),
+            accounts: vec![],
+            data: compute_budget_data(SET_COMPUTE_UNIT_PRICE, &5u64.to_le_bytes()),
+            stack_height: None,
+        }));
+        assert_eq!(compute_unit_price(&message(vec![other_program], &[])), None);
+    }
+
+    #[test]
+    fn nearest_rank_percentiles() {
+        assert_eq!(percentile(&[42], 0.0), 42);
+        assert_eq!(percentile(&[42], 100.0), 42);
+
+        let sorted: Vec<u64> = (1..=100).collect();
+        assert_eq!(percentile(&sorted, 0.0), 1);
+        assert_eq!(percentile(&sorted, 25.0), 25);
+        assert_eq!(percentile(&sorted, 99.0), 99);
+        assert_eq!(percentile(&sorted, 99.5), 100);
+        assert_eq!(percentile(&sorted, 100.0), 100);
+        assert_eq!(percentile(&[1, 2, 3, 4], 50.0), 2);
+    }
+
+    #[test]
+    fn fee_levels_of_samples() {
+        assert!(FeeLevels::from_samples(&[]).is_none());
+
+        let samples: Vec<FeeSample> = (1..=4).map(|price| FeeSample { price, compute_units: price * 1000 }).collect();
+        let levels = FeeLevels::from_samples(&samples).unwrap();
+        assert_eq!(levels.samples, 4);
+        assert_eq!((levels.min, levels.p25, levels.p50, levels.p75, levels.p90, levels.p99, levels.max), (1, 1, 2, 3, 4, 4, 4));
+        assert_eq!(levels.median_compute_units, 2000);
+
+        let other = FeeLevels::from_samples(&[FeeSample { price: 3, compute_units: 500 }]).unwrap();
+        let combined = levels.max_of(other);
+        assert_eq!(combined.samples, 5);
+        assert_eq!((combined.min, combined.p25, combined.p50, combined.max), (3, 3, 3, 4));
+        assert_eq!(combined.median_compute_units, 2000);
+    }
+
+    #[test]
+    fn records_slots_out_of_order() {
+        let estimator = estimator();
+        for (slot, price) in [(105, 50), (103, 30), (104, 40), (103, 31), (101, 10)] {
+            estimator.record(slot, &message(vec![set_price(price)], &[]), &meta(1000));
+        }
+
+        let slots: Vec<(u64, usize)> = estimator.slots.lock().unwrap().iter()
+            .map(|slot_fees| (slot_fees.slot, slot_fees.samples.len()))
+            .collect();
+        assert_eq!(slots, vec![(101, 1), (103, 2), (104, 1), (105, 1)]);
+
+        let estimate = estimator.estimate(&[]);
+        assert_eq!(estimate.slot, 105);
+        let global = estimate.global.unwrap();
+        assert_eq!((global.samples, global.min, global.max), (5, 10, 50));
+    }
+
+    #[test]
+    fn window_excludes_old_slots() {
+        let estimator = estimator();
+        estimator.record(100, &message(vec![set_price(1)], &[]), &meta(1000));
+        estimator.record(100 + WINDOW_SLOTS - 1, &message(vec![set_price(2)], &[]), &meta(1000));
+        assert_eq!(estimator.estimate(&[]).global.unwrap().samples, 2);
+
+        // A new slot pushes the oldest one out of the window, and late samples of slots that
+        // left the window are ignored.
+        estimator.record(100 + WINDOW_SLOTS, &message(vec![set_price(3)], &[]), &meta(1000));
+        estimator.record(100, &message(vec![set_price(4)], &[]), &meta(1000));
+
+        let global = estimator.estimate(&[]).global.unwrap();
+        assert_eq!((global.samples, global.min, global.max), (2, 2, 3));
+    }
+
+    #[test]
+    fn estimates_the_most_contended_writable_account() {
+        let estimator = estimator();
+        let quiet = "Quiet11111111111111111111111111111111111111";
+        estimator.record(100, &message(vec![set_price(1000)], &[BONDING_CURVE]), &meta(1000));
+        estimator.record(100, &message(vec![set_price(2000)], &[BONDING_CURVE, quiet]), &meta(1000));
+        estimator.record(100, &message(vec![], &[quiet]), &meta(1000));
+
+        let estimate = estimator.estimate(&[BONDING_CURVE.to_string(), quiet.to_string(), "Unknown".to_string()]);
+        let global = estimate.global.unwrap();
+        assert_eq!((global.samples, global.min, global.max), (3, 0, 2000));
+
+        let accounts = estimate.accounts.unwrap();
+        assert_eq!((accounts.min, accounts.p50, accounts.max), (1000, 1000, 2000));
+
+        assert!(estimator.estimate(&["Unknown".to_string()]).accounts.is_none());
+    }
+}
@@ -0,0 +1,453 @@
+/// # Solana JSON-RPC Emulation
+/// Answers a subset of the Solana JSON-RPC methods from the state this service already holds in
+/// memory, so that an unmodified Solana client can be pointed at it:
+/// - `getLatestBlockhash`, `getSlot` and `getBlockHeight` from the `BlockhashProcessor`.
+/// - `getBalance` and `getTokenAccountsByOwner` (`jsonParsed` only) for tracked addresses.
+/// - `getSignatureStatuses` for the signatures observed by the `TransactionProcessor`, starting
+///   at the commitment of the transaction source and followed by the `CommitmentTracker`, which
+///   is why `COMMITMENT_RPC_URL` is required alongside `RPC_EMULATOR_ADDR`.
+///
+/// Every other method is answered with a "method not found" error. Commitment parameters are
+/// accepted but ignored, values are always the most recent ones observed.
+///
+/// REQUIRES REDIS: TRUE
+/// - Tracked user addresses.
+
+use axum::{body::Bytes, extract::State, routing::post, Json, Router};
+use std::{net::SocketAddr, sync::Arc};
+use serde_json::{json, Value};
+use tokio::sync::watch;
+use deadpool_redis::Pool;
+use redis::AsyncCommands;
+use tracing::info;
+
+use crate::constants::redis::TRACKED_USER_ADDRESSES;
+
+use super::{
+    blockhashes::BlockhashSnapshot,
+    commitments::commitment::CommitmentStatus,
+    node_state::{NodeState, TokenAccountState}
+};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+/// Same limit as the Solana RPC.
+const MAX_SIGNATURE_STATUSES: usize = 256;
+
+const TOKEN_2022_PROGRAM_ID: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
+
+/// Size of an SPL token account without extensions.
+const TOKEN_ACCOUNT_SPACE: u64 = 165;
+
+type RpcResult = Result<Value, (i64, String)>;
+
+pub struct RpcEmulator {
+    node_state: NodeState,
+    blockhashes: watch::Receiver<BlockhashSnapshot>,
+    slots: watch::Receiver<u64>,
+    redis_pool: Arc<Pool>,
+}
+
+impl RpcEmulator {
+    pub fn new(
+        node_state: NodeState,
+        blockhashes: watch::Receiver<BlockhashSnapshot>,
+        slots: watch::Receiver<u64>,
+        redis_pool: Arc<Pool>
+    ) -> Self {
+        Self {
+            node_state,
+            blockhashes,
+            slots,
+            redis_pool,
+        }
+    }
+
+    pub async fn serve(self, addr: SocketAddr) -> Result<(), std::io::Error> {
+        let app = Router::new()
+            .route("/", post(handle_request))
+            .with_state(Arc::new(self));
+
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        info!("Serving Solana JSON-RPC on {}", addr);
+        axum::serve(listener, app).await
+    }
+
+    /// Handles a single request object, batches are split up by `handle_request`.
+    async fn handle_call(&self, request: &Value) -> Value {
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let method = match request.get("method").and_then(Value::as_str) {
+            Some(method) if request.get("jsonrpc").and_then(Value::as_str) == Some("2.0") => method,
+            _ => return error_response(id, INVALID_REQUEST, "Invalid request".to_string()),
+        };
+
+        let params = match request.get("params") {
+            Some(Value::Array(params)) => params.as_slice(),
+            None | Some(Value::Null) => &[],
+            Some(_) => return error_response(id, INVALID_PARAMS, "Invalid params".to_string()),
+        };
+
+        let result = match method {
+            "getLatestBlockhash" => self.get_latest_blockhash(),
+            "getSlot" => self.get_slot(),
+            "getBlockHeight" => self.get_block_height(),
+            "getBalance" => self.get_balance(params).await,
+            "getTokenAccountsByOwner" => self.get_token_accounts_by_owner(params).await,
+            "getSignatureStatuses" => self.get_signature_statuses(params),
+            _ => Err((METHOD_NOT_FOUND, "Method not found".to_string())),
+        };
+
+        match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+            Err((code, message)) => error_response(id, code, message),
+        }
+    }
+
+    fn current_slot(&self) -> Result<u64, (i64, String)> {
+        match *self.slots.borrow() {
+            0 => Err((INTERNAL_ERROR, "No slot has been observed yet".to_string())),
+            slot => Ok(slot),
+        }
+    }
+
+    fn get_latest_blockhash(&self) -> RpcResult {
+        let snapshot = self.blockhashes.borrow();
+        let latest = snapshot.latest()
+            .ok_or_else(|| (INTERNAL_ERROR, "No blockhash has been observed yet".to_string()))?;
+
+        Ok(json!({
+            "context": { "slot": latest.slot },
+            "value": {
+                "blockhash": latest.blockhash,
+                "lastValidBlockHeight": latest.last_valid_block_height,
+            }
+        }))
+    }
+
+    fn get_slot(&self) -> RpcResult {
+        Ok(json!(self.current_slot()?))
+    }
+
+    fn get_block_height(&self) -> RpcResult {
+        match self.blockhashes.borrow().current_block_height {
+            0 => Err((INTERNAL_ERROR, "No block height has been observed yet".to_string())),
+            block_height => Ok(json!(block_height)),
+        }
+    }
+
+    async fn get_balance(&self, params: &[Value]) -> RpcResult {
+        let address = address_param(params)?;
+        self.ensure_tracked(address).await?;
+
+        let (slot, lamports) = self.node_state.balance(address)
+            .ok_or_else(|| (INVALID_PARAMS, format!("No balance has been observed yet for {}", address)))?;
+
+        Ok(json!({
+            "context": { "slot": slot },
+            "value": lamports,
+        }))
+    }
+
+    async fn get_token_accounts_by_owner(&self, params: &[Value]) -> RpcResult {
+        let owner = address_param(params)?;
+
+        let filter = params.get(1).and_then(Value::as_object)
+            .ok_or_else(|| (INVALID_PARAMS, "Missing mint or programId filter".to_string()))?;
+        let (mint, program_id) = match (filter.get("mint"), filter.get("programId")) {
+            (Some(Value::String(mint)), None) => (Some(mint.as_str()), None),
+            (None, Some(Value::String(program_id))) => (None, Some(program_id.as_str())),
+            _ => return Err((INVALID_PARAMS, "Expected either a mint or a programId filter".to_string())),
+        };
+
+        let encoding = params.get(2).and_then(|config| config.get("encoding")).and_then(Value::as_str);
+        if encoding != Some("jsonParsed") {
+            return Err((INVALID_PARAMS, "Only the jsonParsed encoding is supported".to_string()));
+        }
+
+        self.ensure_tracked(owner).await?;
+
+        let token_accounts: Vec<Value> = self.node_state.token_accounts_by_owner(owner)
+            .iter()
+            .filter(|token_account| mint.map_or(true, |mint| token_account.mint == mint))
+            .filter(|token_account| program_id.map_or(true, |program_id| token_account.program_id == program_id))
+            .map(token_account_json)
+            .collect();
+
+        Ok(json!({
+            "context": { "slot": self.current_slot()? },
+            "value": token_accounts,
+        }))
+    }
+
+    fn get_signature_statuses(&self, params: &[Value]) -> RpcResult {
+        let signatures = params.first().and_then(Value::as_array)
+            .ok_or_else(|| (INVALID_PARAMS, "Expected an array of signatures".to_string()))?;
+        if signatures.len() > MAX_SIGNATURE_STATUSES {
+            return Err((INVALID_PARAMS, format!("Too many inputs provided; max {}", MAX_SIGNATURE_STATUSES)));
+        }
+
+        let statuses: Vec<Value> = signatures.iter()
+            .map(|signature| {
+                let state = self.node_state.signature_status(signature.as_str()?)?;
+                let (confirmations, confirmation_status) = match state.status {
+                    CommitmentStatus::Finalized => (Value::Null, "finalized"),
+                    CommitmentStatus::Confirmed => (json!(0), "confirmed"),
+                    _ => (json!(0), "processed"),
+                };
+                let status = match &state.err {
+                    Some(err) => json!({ "Err": err }),
+                    None => json!({ "Ok": null }),
+                };
+
+                Some(json!({
+                    "slot": state.slot,
+                    "confirmations": confirmations,
+                    "err": state.err,
+                    "status": status,
+                    "confirmationStatus": confirmation_status,
+                }))
+            })
+            .map(|status| status.unwrap_or(Value::Null))
+            .collect();
+
+        Ok(json!({
+            "context": { "slot": self.current_slot()? },
+            "value": statuses,
+        }))
+    }
+
+    /// Balances are only followed for tracked addresses, answering for any other address would
+    /// silently return outdated values.
+    async fn ensure_tracked(&self, address: &str) -> Result<(), (i64, String)> {
+        let mut conn = self.redis_pool.get().await
+            .map_err(|e| (INTERNAL_ERROR, format!("Redis pool error: {}", e)))?;
+        let tracked: bool = conn.sismember(TRACKED_USER_ADDRESSES, address).await
+            .map_err(|e| (INTERNAL_ERROR, format!("Redis SISMEMBER error: {}", e)))?;
+
+        if tracked {
+            Ok(())
+        } else {
+            Err((INVALID_PARAMS, format!("Address {} is not tracked by this node", address)))
+        }
+    }
+}
+
+async fn handle_request(State(emulator): State<Arc<RpcEmulator>>, body: Bytes) -> Json<Value> {
+    let request: Value = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(_) => return Json(error_response(Value::Null, PARSE_ERROR, "Parse error".to_string())),
+    };
+
+    match request {
+        Value::Array(requests) if !requests.is_empty() => {
+            let mut responses = Vec::with_capacity(requests.len());
+            for request in &requests {
+                responses.push(emulator.handle_call(request).await);
+            }
+            Json(Value::Array(responses))
+        }
+        Value::Object(_) => Json(emulator.handle_call(&request).await),
+        _ => Json(error_response(Value::Null, INVALID_REQUEST, "Invalid request".to_string())),
+    }
+}
+
+fn error_response(id: Value, code: i64, message: String) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "error": { "code": code, "message": message },
+        "id": id,
+    })
+}
+
+fn address_param(params: &[Value]) -> Result<&str, (i64, String)> {
+    params.first()
+        .and_then(Value::as_str)
+        .ok_or_else(|| (INVALID_PARAMS, "Expected an address as the first parameter".to_string()))
+}
+
+fn token_account_json(token_account: &TokenAccountState) -> Value {
+    let ui_amount = token_account.amount as f64 / 10f64.powi(token_account.decimals as i32);
+    let program = match token_account.program_id.as_str() {
+        TOKEN_2022_PROGRAM_ID => "spl-token-2022",
+        _ => "spl-token",
+    };
+
+    json!({
+        "pubkey": token_account.address,
+        "account": {
+            "data": {
+                "program": program,
+                "parsed": {
+                    "info": {
+                        "isNative": false,
+                        "mint": token_account.mint,
+                        "owner": token_account.owner,
+                        "state": "initialized",
+                        "tokenAmount": {
+                            "amount": token_account.amount.to_string(),
+                            "decimals": token_account.decimals,
+                            "uiAmount": ui_amount,
+                            "uiAmountString": ui_amount.to_string(),
+                        }
+                    },
+                    "type": "account"
+                },
+                "space": TOKEN_ACCOUNT_SPACE,
+            },
+            "executable": false,
+            "lamports": token_account.lamports,
+            "owner": token_account.program_id,
+            "rentEpoch": u64::MAX,
+            "space": TOKEN_ACCOUNT_SPACE,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use deadpool_redis::{Config, Runtime};
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+    use std::time::Instant;
+    use crate::processors::blockhashes::SlotBlockhash;
+
+    /// An emulator at slot 250 with a single blockhash. Redis is unreachable.
+    fn emulator(node_state: NodeState) -> Arc<RpcEmulator> {
+        let snapshot = BlockhashSnapshot {
+            recent_hashes: vec![SlotBlockhash {
+                slot: 240,
+                blockhash: "4sGjMW1sUnHzSxGspuhpqLDx6wiyjNtZAMdL4VZHirAn".to_string(),
+                last_valid_block_height: 380,
+                time: Instant::now(),
+                observed_at: 0,
+            }],
+            current_block_height: 230,
+            avg_block_time: 0.4,
+        };
+        let (_, blockhashes) = watch::channel(snapshot);
+        let (_, slots) = watch::channel(250);
+        let redis_pool = Config::from_url("redis://127.0.0.1:1").create_pool(Some(Runtime::Tokio1)).unwrap();
+
+        Arc::new(RpcEmulator::new(node_state, blockhashes, slots, Arc::new(redis_pool)))
+    }
+
+    async fn call(emulator: &Arc<RpcEmulator>, body: &str) -> Value {
+        let Json(response) = handle_request(State(emulator.clone()), Bytes::from(body.to_string())).await;
+        response
+    }
+
+    #[tokio::test]
+    async fn answers_batches_in_order() {
+        let emulator = emulator(NodeState::new());
+        let response = call(&emulator, r#"[
+            {"jsonrpc":"2.0","id":1,"method":"getSlot"},
+            {"jsonrpc":"2.0","id":"two","method":"getLatestBlockhash","params":[{"commitment":"finalized"}]},
+            {"jsonrpc":"2.0","id":3,"method":"getBlockHeight"},
+            {"jsonrpc":"2.0","id":4,"method":"sendTransaction","params":[]}
+        ]"#).await;
+
+        assert_eq!(response, json!([
+            { "jsonrpc": "2.0", "result": 250, "id": 1 },
+            {
+                "jsonrpc": "2.0",
+                "result": {
+                    "context": { "slot": 240 },
+                    "value": {
+                        "blockhash": "4sGjMW1sUnHzSxGspuhpqLDx6wiyjNtZAMdL4VZHirAn",
+                        "lastValidBlockHeight": 380,
+                    }
+                },
+                "id": "two"
+            },
+            { "jsonrpc": "2.0", "result": 230, "id": 3 },
+            { "jsonrpc": "2.0", "error": { "code": METHOD_NOT_FOUND, "message": "Method not found" }, "id": 4 },
+        ]));
+    }
+
+    #[tokio::test]
+    async fn reports_malformed_requests() {
+        let emulator = emulator(NodeState::new());
+        let error_code = |response: &Value| response["error"]["code"].as_i64();
+
+        let response = call(&emulator, r#"{"jsonrpc":"2.0","id":1,"method":"#).await;
+        assert_eq!(error_code(&response), Some(PARSE_ERROR));
+        assert_eq!(response["id"], Value::Null);
+
+        for body in ["[]", "42", r#""getSlot""#] {
+            let response = call(&emulator, body).await;
+            assert_eq!(error_code(&response), Some(INVALID_REQUEST), "{}", body);
+            assert_eq!(response["id"], Value::Null);
+        }
+
+        let response = call(&emulator, r#"{"jsonrpc":"1.0","id":7,"method":"getSlot"}"#).await;
+        assert_eq!(error_code(&response), Some(INVALID_REQUEST));
+        assert_eq!(response["id"], 7);
+
+        let response = call(&emulator, r#"{"jsonrpc":"2.0","id":8,"method":"getSlot","params":{}}"#).await;
+        assert_eq!(error_code(&response), Some(INVALID_PARAMS));
+        assert_eq!(response["id"], 8);
+        assert!(response.get("result").is_none());
+    }
+
+    #[tokio::test]
+    async fn answers_signature_statuses_from_the_node_state() {
+        let node_state = NodeState::new();
+        node_state.record_signature("succeeded", 240, None, CommitmentStatus::Processed);
+        node_state.set_commitment("succeeded", 240, CommitmentStatus::Finalized);
+        let err = TransactionError::InstructionError(0, InstructionError::Custom(6003));
+        node_state.record_signature("failed", 245, Some(err), CommitmentStatus::Confirmed);
+
+        let emulator = emulator(node_state);
+        let response = call(
+            &emulator,
+            r#"{"jsonrpc":"2.0","id":1,"method":"getSignatureStatuses","params":[["succeeded","unknown","failed"]]}"#
+        ).await;
+
+        assert_eq!(response["result"], json!({
+            "context": { "slot": 250 },
+            "value": [
+                {
+                    "slot": 240,
+                    "confirmations": null,
+                    "err": null,
+                    "status": { "Ok": null },
+                    "confirmationStatus": "finalized",
+                },
+                null,
+                {
+                    "slot": 245,
+                    "confirmations": 0,
+                    "err": { "InstructionError": [0, { "Custom": 6003 }] },
+                    "status": { "Err": { "InstructionError": [0, { "Custom": 6003 }] } },
+                    "confirmationStatus": "confirmed",
+                },
+            ]
+        }));
+
+        let signatures = vec!["unknown"; MAX_SIGNATURE_STATUSES + 1];
+        let request = json!({ "jsonrpc": "2.0", "id": 2, "method": "getSignatureStatuses", "params": [signatures] });
+        let response = call(&emulator, &request.to_string()).await;
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn validates_params_before_reaching_redis() {
+        let emulator = emulator(NodeState::new());
+
+        let response = call(&emulator, r#"{"jsonrpc":"2.0","id":1,"method":"getBalance","params":[]}"#).await;
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+
+        let response = call(
+            &emulator,
+            r#"{"jsonrpc":"2.0","id":2,"method":"getTokenAccountsByOwner","params":["owner",{"mint":"mint"},{"encoding":"base64"}]}"#
+        ).await;
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+
+        let response = call(&emulator, r#"{"jsonrpc":"2.0","id":3,"method":"getBalance","params":["owner"]}"#).await;
+        assert_eq!(response["error"]["code"], INTERNAL_ERROR);
+    }
+}
@@ -1,32 +1,21 @@
+use solana_sdk::transaction::TransactionError;
 use solana_transaction_status::{
-    EncodedTransaction, 
-    EncodedTransactionWithStatusMeta, 
+    option_serializer::OptionSerializer,
     UiCompiledInstruction, 
     UiInstruction, 
-    UiMessage, 
     UiParsedInstruction
 };
-use tokio_tungstenite::{
-    connect_async, 
-    MaybeTlsStream, 
-    WebSocketStream, 
-    tungstenite::{Error as WsError, Message as WsMessage}
-};
-use solana_transaction_status::option_serializer::OptionSerializer;
+use tokio_tungstenite::tungstenite::Error as WsError;
 use gimpey_db_gateway::SerumMarketClient;
-use std::{collections::HashSet, fs::{self, File}, io};
-use tokio::{net::TcpStream, sync::Mutex};
-use futures_util::{SinkExt, StreamExt};
-use tokio::sync::mpsc::UnboundedSender;
-use serde::{Serialize, Deserialize};
-use serde_json::{json, Value};
-use futures::{stream::{SplitSink, SplitStream}, TryStreamExt};
+use std::{collections::HashSet, fs::{self, File}};
+use tokio::sync::mpsc::{self, UnboundedSender};
+use futures::TryStreamExt;
 use tracing::{info, warn};
 use redis::AsyncCommands;
 use deadpool_redis::Pool;
 use std::path::Path;
 use std::io::Write;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use prost::Message;
 use yansi::Paint;
 
@@ -35,10 +24,13 @@ use crate::{
         redis::TRACKED_USER_ADDRESSES, 
         zmq::{LAMPORTS_BALANCE_UPDATE, SPL_TOKEN_BALANCE_UPDATE}
     }, 
+    helpers::{address_lookup_tables::AddressLookupTableCache, signature_cache::SignatureCache},
     instructions::raydium::initialize_two::initialize_two_handler, 
-    transaction_helpers::compile_balance_updates::compile_balance_updates
+    sources::{backfill::Backfiller, NotificationStream, SourceError, TransactionNotificationResult, TransactionSource},
+    transaction_helpers::{compile_balance_updates::compile_balance_updates, decode_transaction::decode_transaction}
 };
 use crate::instructions::serum::initialize_market::initialize_market_handler;
+use crate::instructions::failed::failed_instruction_handler;
 use crate::programs::daos_fund_deployer::DaosFundDeployerFunction;
 use crate::instructions::{daos_fund, pump_fun};
 use crate::programs::pump_fun::PumpFunFunction;
@@ -47,6 +39,10 @@ use crate::programs::serum::SerumFunction;
 use crate::messaging::MpscMessage;
 use crate::programs::ProgramId;
 
+use super::commitments::CommitmentTracker;
+use super::priority_fees::PriorityFeeEstimator;
+use super::node_state::{NodeState, TokenAccountState};
+
 pub mod spl_token {
     tonic::include_proto!("spl_token");
 }
@@ -58,302 +54,253 @@ pub mod system {
 use system::LamportsBalanceUpdate;
 use spl_token::SplBalanceUpdate;
 
+/// Number of recently processed signatures remembered to avoid handling a transaction twice
+/// when a backfill overlaps with the live stream.
+const PROCESSED_SIGNATURES_CAPACITY: usize = 50_000;
+
+/// Owner of token accounts whose balance does not state its token program.
+const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
 #[derive(Clone)]
 pub struct TransactionProcessor {
-    api_key: String,
-    url: String,
-    ws_write: Arc<Mutex<Option<SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, WsMessage>>>>,
-    ws_read: Arc<Mutex<Option<SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>>>>,
+    source: Arc<dyn TransactionSource>,
+    backfiller: Option<Arc<Backfiller>>,
+    commitment_tracker: Option<CommitmentTracker>,
+    lookup_tables: Option<Arc<AddressLookupTableCache>>,
+    priority_fees: Option<PriorityFeeEstimator>,
+    node_state: Option<NodeState>,
+    /// Whether bonding curve updates derived from trades are published. Disabled when the
+    /// exact values are published by the `BondingCurveProcessor`, on the same topic.
+    derived_bonding_curves: bool,
+    /// Most recent slot processed from `source`. The sources merged by a `MultiSource` each
+    /// reconnect on their own, their gaps are backfilled by the `MultiSource` itself.
+    last_slot: Arc<Mutex<Option<u64>>>,
+    processed_signatures: Arc<Mutex<SignatureCache>>,
     tx: UnboundedSender<MpscMessage>,
     redis_pool: Arc<Pool>,
     serum_market_client: SerumMarketClient
 }
 
-/// https://github.com/helius-labs/helius-rust-sdk/blob/dev/src/types/enhanced_websocket.rs#L96
-#[derive(Debug, Serialize, Deserialize)]
-pub struct TransactionNotificationResult {
-    transaction: EncodedTransactionWithStatusMeta,
-    signature: String,
-    slot: u64,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct TransactionNotificationParams {
-    subscription: u64,
-    result: TransactionNotificationResult
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct TransactionNotification {
-    jsonrpc: String,
-    method: String,
-    params: TransactionNotificationParams
-}
-
-#[allow(dead_code)]
-#[derive(Debug, Deserialize)]
-pub struct SubscriptionSuccessfulNotification {
-    id: String,
-    jsonrpc: String,
-    result: u64
-}
-
-#[allow(dead_code)]
-#[derive(Debug, Serialize, Deserialize)]
-pub struct SubscriptionErrorParams {
-    subscription: u64,
-    error: String
-} 
-
-#[allow(dead_code)]
-#[derive(Debug, Deserialize)]
-pub struct SubscriptionErrorNotification {
-    jsonrpc: String,
-    method: String,
-    params: SubscriptionErrorParams
-}
-
 impl TransactionProcessor {
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
-        api_key: &str, 
-        url: &str, 
+        source: Arc<dyn TransactionSource>,
+        backfiller: Option<Arc<Backfiller>>,
+        commitment_tracker: Option<CommitmentTracker>,
+        lookup_tables: Option<AddressLookupTableCache>,
+        priority_fees: Option<PriorityFeeEstimator>,
+        node_state: Option<NodeState>,
+        derived_bonding_curves: bool,
         tx: UnboundedSender<MpscMessage>, 
         redis_pool: Arc<Pool>,
         serum_market_client: SerumMarketClient
     ) -> Result<Self, WsError> {
         Ok(Self {
-            api_key: api_key.to_string(),
-            url: url.to_string(),
-            ws_write: Arc::new(Mutex::new(None)),
-            ws_read: Arc::new(Mutex::new(None)),
+            source,
+            backfiller,
+            commitment_tracker,
+            lookup_tables: lookup_tables.map(Arc::new),
+            priority_fees,
+            node_state,
+            derived_bonding_curves,
+            last_slot: Arc::new(Mutex::new(None)),
+            processed_signatures: Arc::new(Mutex::new(SignatureCache::new(PROCESSED_SIGNATURES_CAPACITY))),
             tx,
             redis_pool,
             serum_market_client
         })
     }
 
-    /// Runs the three step process to start the transaction processor.
+    /// Subscribes to the configured `TransactionSource` and processes its notifications,
+    /// re-subscribing whenever the underlying connection is lost. Returns once a source that
+    /// does not reconnect (e.g. a replay) has ended.
     pub async fn start_processor(&self) {
         loop {
-            if let Err(err) = self.start_connection().await {
-                warn!("Failed to establish connection to WebSocket: {}. Retrying in 1 second(s)...", err);
-                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-                continue;
-            }
-
-            if let Err(err) = self.subscribe_to_transactions().await {
-                warn!("Failed to subscribe to transactions: {}. Retrying in 1 second(s)...", err);
-                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-                continue;
-            }
-
-            match self.process_messages().await {
-                Ok(_) => {
-                    info!("Message processing ended gracefully (unexpected). Stopping...");
-                    break;
-                }
+            let stream = match self.source.subscribe().await {
+                Ok(stream) => stream,
                 Err(err) => {
-                    warn!("Error while processing messages: {}. Will retry in 1 second(s)...", err);
+                    warn!("Failed to subscribe to {}: {}. Retrying in 1 second(s)...", self.source.name(), err);
                     tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                    continue;
                 }
-            }
-        }
-    }
-
-    pub async fn start_connection(&self) -> Result<(), WsError> {
-        let ws_url = format!("wss://{}/?api-key={}", self.url, self.api_key);
-        info!("Connecting to: wss://{}/?api-key=*", self.url);
-
-        let (ws, _response) = connect_async(ws_url).await?;
-        info!("WebSocket connection established!");
-
-        let (write, read) = ws.split();
+            };
 
-        *self.ws_write.lock().await = Some(write);
-        *self.ws_read.lock().await = Some(read);
+            // The live stream is already buffering at this point, so once the gap has been
+            // backfilled nothing that landed in between can be missed.
+            self.backfill_gap().await;
 
-        Ok(())
-    }
-    
-    pub async fn subscribe_to_transactions(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let request = json!({
-            "jsonrpc": "2.0",
-            "id": "1",
-            "method": "transactionSubscribe",
-            "params": [
-                {
-                    "vote": false,
-                    "failed": false,
-                    "accountInclude": [],
-                    "accountRequired": [],
-                    "accountExclude": [],
-                },
-                {
-                    "commitment": "processed",
-                    "encoding": "jsonParsed",
-                    "transaction_details": "full",
-                    "showRewards": true,
-                    "maxSupportedTransactionVersion": 0,
+            // A stream ending gracefully (e.g. the server sending a close frame) is a
+            // disconnect like any other, it is resubscribed and the gap backfilled.
+            match self.process_notifications(stream).await {
+                Ok(_) if !self.source.reconnects() => {
+                    info!("{} finished.", self.source.name());
+                    return;
                 }
-            ]
-        });
-
-        let request_string = request.to_string();
-
-        info!("Unlocking WebSocket mutex...");
-
-        let mut ws_guard = self.ws_write.lock().await;
-
-        info!("WebSocket mutex unlocked.");
-
-        if let Some(ws) = ws_guard.as_mut() {
-            ws.send(WsMessage::Text(request_string)).await?;
-            info!("Subscription to transactions successfully sent.");
-            Ok(())
-        } else {
-            Err("WebSocket is not connected!".into())
+                Ok(_) => warn!("{} stream ended. Will reconnect in 1 second(s)...", self.source.name()),
+                Err(err) => warn!("Error while processing messages: {}. Will retry in 1 second(s)...", err),
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
         }
     }
 
-    pub async fn process_messages(&self) -> Result<(), WsError> {
-        let mut read_guard = self.ws_read.lock().await;
-        let read = read_guard.take().ok_or_else(|| {
-            WsError::Io(io::Error::new(
-                io::ErrorKind::Other,
-                "Read stream is not connected!",
-            ))
-        })?;
-
-        let write_arc = self.ws_write.clone();
-        let heartbeat = tokio::spawn(async move {
-            loop {
-                tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
-
-                let mut write_guard = write_arc.lock().await;
-                if let Some(ref mut sink) = *write_guard {
-                    if let Err(err) = sink.send(WsMessage::Ping(vec![])).await {
-                        eprintln!("Failed to send ping: {:?}", err);
-                        break;
-                    }
-                } else {
-                    eprintln!("Write sink is None, stopping heartbeat.");
-                    break;
-                }
-            }
-        });
-
+    pub async fn process_notifications(&self, stream: NotificationStream) -> Result<(), SourceError> {
         // We could use `try_for_each_concurrent` here to process multiple messages concurrently.
         // However, this leads to issues considering Solana transactions are ordered. If a later
         // transaction is processed before an earlier one (block ordering) due to concurrency,
         // we may feed faulty data to the database. Therefore, we must process messages sequentially.
         // We could try implement some timestamp logic to determine the most recent transaction prior
         // to any asynchronous processing, but this is a bit more complex.
-        let result = read.try_for_each(|msg| {
-                let this = self.clone();
-                async move { this.handle_message(msg).await }
+        let result = stream.try_for_each(|notification| {
+            let this = self.clone();
+            async move {
+                this.process_notification(notification).await;
+                Ok(())
+            }
         }).await;
 
-        heartbeat.abort();
-
         match result {
             Ok(()) => {
                 // If we reach here, it means the stream ended gracefully (connection closed).
-                warn!("WebSocket connection closed.");
+                warn!("{} stream closed.", self.source.name());
                 Ok(())
             }
             Err(e) => {
-                // If we encounter an error (like a subscription error that couldn't be fixed 
-                // or a read error), this will trigger the logic in `start_processor` to 
-                // reconnect and/or resubscribe.
-                warn!("Error processing messages: {}", e);
+                warn!("Error processing messages from {}: {}", self.source.name(), e);
                 Err(e)
             }
         }
     }
 
-    async fn handle_message(&self, message: WsMessage) -> Result<(), WsError> {
-        match message {
-            WsMessage::Text(text) => {
-                let json: Value = serde_json::from_str(&text)
-                    .map_err(|e| WsError::Io(io::Error::new(io::ErrorKind::Other, format!("JSON parse error: {}", e))))?;
-    
-                if let Ok(notification) = serde_json::from_value::<TransactionNotification>(json.clone()) {
-                    let _ = self.handle_transaction_notification(notification).await;
-                    return Ok(());
-                }
-    
-                if let Ok(notification) = serde_json::from_value::<SubscriptionSuccessfulNotification>(json.clone()) {
-                    info!("Subscription successful: {:?}", notification);
-                    return Ok(());
-                }
+    /// Replays everything since the last processed slot through the handlers. Does nothing on
+    /// the very first connection, or when no backfill RPC endpoint is configured.
+    async fn backfill_gap(&self) {
+        let last_slot = *self.last_slot.lock().unwrap();
+        let (backfiller, last_slot) = match (&self.backfiller, last_slot) {
+            (Some(backfiller), Some(last_slot)) => (backfiller, last_slot),
+            (None, Some(last_slot)) => {
+                warn!("Reconnected to {} without a backfill RPC, transactions since slot {} may be missing.", self.source.name(), last_slot);
+                return;
+            }
+            _ => return,
+        };
 
-                if let Ok(notification) = serde_json::from_value::<SubscriptionErrorNotification>(json.clone()) {
-                    warn!("Subscription error: {:?}", notification);
-                    warn!("Sending re-subscription request...");
-    
-                    self.subscribe_to_transactions().await.map_err(|e| {
-                        WsError::Io(io::Error::new(io::ErrorKind::Other, format!("Failed to re-subscribe after error: {}", e)))
-                    })?;
-    
-                    info!("Re-subscription successful after error.");
-                    return Ok(());
+        match backfiller.backfill(last_slot).await {
+            Ok(notifications) => {
+                for notification in notifications {
+                    self.process_notification(notification).await;
                 }
+                info!("Backfill since slot {} completed, resuming live processing.", last_slot);
+            }
+            Err(err) => warn!("Failed to backfill since slot {}: {}", last_slot, err),
+        }
+    }
 
-                warn!("Unknown JSON message: {}", text);
-                Ok(())
+    /// Handles a notification unless its signature was already processed, keeping track of the
+    /// most recent slot seen.
+    async fn process_notification(&self, notification: TransactionNotificationResult) {
+        if !self.processed_signatures.lock().unwrap().insert(&notification.signature) {
+            return;
+        }
+
+        let slot = notification.slot;
+        let signature = notification.signature.clone();
+
+        if let Some(node_state) = &self.node_state {
+            let err = notification.transaction.meta.as_ref().and_then(|meta| meta.err.clone());
+            node_state.record_signature(&signature, slot, err, self.source.commitment());
+        }
+
+        let published = match &self.commitment_tracker {
+            Some(_) => {
+                // The handlers publish through a dedicated channel so that we know whether this
+                // transaction produced any events whose commitment needs to be followed.
+                let (tx, mut rx) = mpsc::unbounded_channel();
+                let this = Self { tx, ..self.clone() };
+                let _ = this.handle_transaction_notification(notification).await;
+                drop(this);
+
+                let mut published = false;
+                while let Some(message) = rx.recv().await {
+                    self.tx.send(message).expect("Failed to send MPSC Message.");
+                    published = true;
+                }
+                published
             }
-            WsMessage::Pong(_data) => {
-                info!("WebSocket server responded with Pong.");
-                Ok(())
+            None => {
+                let _ = self.handle_transaction_notification(notification).await;
+                false
             }
-            other => {
-                println!("Unknown message: {:?}", other);
-                Ok(())
+        };
+
+        // The JSON-RPC emulator reports the status of every signature it remembers, not only
+        // of those events were published for.
+        if let Some(tracker) = &self.commitment_tracker {
+            if published || self.node_state.is_some() {
+                tracker.track(&signature, slot, published);
             }
         }
+
+        let mut last_slot = self.last_slot.lock().unwrap();
+        *last_slot = Some(last_slot.map_or(slot, |last| last.max(slot)));
     }
 
-    async fn handle_transaction_notification(&self, notification: TransactionNotification) -> Result<(), WsError> {
-        let meta = match &notification.params.result.transaction.meta {
+    pub async fn handle_transaction_notification(&self, notification: TransactionNotificationResult) -> Result<(), WsError> {
+        let meta = match &notification.transaction.meta {
             Some(meta) => meta,
             None => {
-                panic!("Transaction meta is missing!");
+                return Err(WsError::Io(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Transaction meta is missing for {}", notification.signature)
+                )));
             }
         };
 
-        let transaction = match &notification.params.result.transaction.transaction {
-            EncodedTransaction::Json(ui_transaction) => ui_transaction,
-            _ => {
-                panic!("Binary transactions are not supported!");
-            }
-        };
+        let decoded = decode_transaction(&notification.transaction, self.lookup_tables.as_deref()).await.map_err(|e| {
+            warn!("Failed to decode transaction {}: {}", notification.signature, e);
+            WsError::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+        })?;
 
-        let message = match &transaction.message {
-            UiMessage::Parsed(ui_message) => ui_message,
-            _ => {
-                panic!("Binary messages are not supported!");
-            }
-        };
+        let accounts = &decoded.message.account_keys;
 
-        let accounts = &message.account_keys;
+        if let Some(priority_fees) = &self.priority_fees {
+            priority_fees.record(notification.slot, &decoded.message, meta);
+        }
 
         let mut compiled_instructions: Vec<UiCompiledInstruction> = Vec::new();
         let mut parsed_instructions: Vec<UiParsedInstruction> = Vec::new();
 
-        for instruction in message.instructions.iter() {
-            match instruction {
-                UiInstruction::Parsed(ui_instruction) => parsed_instructions.push(ui_instruction.clone()),
-                UiInstruction::Compiled(ui_instruction) => compiled_instructions.push(ui_instruction.clone())
+        let inner_instructions = decoded.inner_instructions.iter().flat_map(|ui_inner| ui_inner.instructions.iter());
+
+        // A failed transaction did not change any program state, so it is only reported through
+        // `failed_instruction` and never reaches the program handlers. Fees were still charged,
+        // which is why the balance updates below are processed either way.
+        if let Some(error) = &meta.err {
+            if let TransactionError::InstructionError(index, _) = error {
+                let failed_group: Vec<UiInstruction> = decoded.message.instructions
+                    .get(*index as usize)
+                    .into_iter()
+                    .chain(decoded.inner_instructions
+                        .iter()
+                        .filter(|ui_inner| ui_inner.index == *index)
+                        .flat_map(|ui_inner| ui_inner.instructions.iter()))
+                    .cloned()
+                    .collect();
+
+                failed_instruction_handler(
+                    notification.slot,
+                    error,
+                    &failed_group,
+                    accounts,
+                    meta,
+                    &notification.signature,
+                    self.tx.clone()
+                );
             }
-        }
-
-        if let OptionSerializer::Some(inner_instructions) = &meta.inner_instructions {
-            for ui_inner in inner_instructions {
-                for inner_instruction in ui_inner.instructions.iter() {
-                    match inner_instruction {
-                        UiInstruction::Parsed(ui_instruction) => parsed_instructions.push(ui_instruction.clone()),
-                        UiInstruction::Compiled(ui_instruction) => compiled_instructions.push(ui_instruction.clone())
-                    }
+        } else {
+            for instruction in decoded.message.instructions.iter().chain(inner_instructions) {
+                match instruction {
+                    UiInstruction::Parsed(ui_instruction) => parsed_instructions.push(ui_instruction.clone()),
+                    UiInstruction::Compiled(ui_instruction) => compiled_instructions.push(ui_instruction.clone())
                 }
             }
         }
@@ -364,33 +311,30 @@ impl TransactionProcessor {
                 UiParsedInstruction::PartiallyDecoded(ui_instruction) => {
                     let program_address = &ui_instruction.program_id;
 
-                    if let Some(program_id) = ProgramId::from_str(&program_address) {
+                    if let Some(program_id) = ProgramId::from_address(program_address) {
                         match program_id {
                             ProgramId::PumpFun => {
                                 if let Some(instruction_type) = PumpFunFunction::from_data(&ui_instruction.data) {
                                     match instruction_type {
                                         PumpFunFunction::Creation => pump_fun::creation::creation_handler(
-                                            notification.params.result.slot,
-                                            &ui_instruction,
+                                            notification.slot,
+                                            ui_instruction,
                                             accounts,
-                                            &meta,
-                                            &notification.params.result.signature,
+                                            meta,
+                                            &notification.signature,
                                             self.tx.clone()
                                         ),
-                                        PumpFunFunction::Buy => pump_fun::trade::trade_handler(
-                                            &ui_instruction,
-                                            accounts,
-                                            &meta,
-                                            self.redis_pool.clone(),
-                                            self.tx.clone()
-                                        ).await?,
-                                        PumpFunFunction::Sell => pump_fun::trade::trade_handler(
-                                            &ui_instruction,
-                                            accounts,
-                                            &meta,
-                                            self.redis_pool.clone(),
-                                            self.tx.clone()
-                                        ).await?,
+                                        PumpFunFunction::Buy | PumpFunFunction::Sell if self.derived_bonding_curves => {
+                                            pump_fun::trade::trade_handler(
+                                                notification.slot,
+                                                ui_instruction,
+                                                accounts,
+                                                meta,
+                                                self.redis_pool.clone(),
+                                                self.tx.clone()
+                                            ).await?
+                                        }
+                                        PumpFunFunction::Buy | PumpFunFunction::Sell => {}
                                     }
                                 }
                             },
@@ -399,11 +343,11 @@ impl TransactionProcessor {
                                     match instruction_type {
                                         DaosFundDeployerFunction::InitializeCurve => {
                                             daos_fund::initialize_curve::initialize_curve_handler(
-                                                notification.params.result.slot, 
-                                                &ui_instruction, 
+                                                notification.slot, 
+                                                ui_instruction, 
                                                 accounts, 
-                                                &meta, 
-                                                &notification.params.result.signature, 
+                                                meta, 
+                                                &notification.signature, 
                                                 self.tx.clone()
                                             );
                                             info!("Daos Fund Deployer InitializeCurve");
@@ -426,10 +370,10 @@ impl TransactionProcessor {
                                 if let Some(instruction_type) = SerumFunction::from_data(&ui_instruction.data) {
                                     match instruction_type {
                                         SerumFunction::InitializeMarket => initialize_market_handler(
-                                            &ui_instruction, 
+                                            ui_instruction, 
                                             accounts, 
                                             self.tx.clone(),
-                                            &notification.params.result.signature,
+                                            &notification.signature,
                                             self.serum_market_client.clone()
                                         ).await,
                                     }
@@ -440,9 +384,9 @@ impl TransactionProcessor {
                                     match instruction_type {
                                         RaydiumFunction::Initialize => info!("Raydium Initialize"),
                                         RaydiumFunction::Initialize2 => initialize_two_handler(
-                                            &ui_instruction,
+                                            ui_instruction,
                                             accounts,
-                                            &notification.params.result.signature
+                                            &notification.signature
                                         )
                                     }
                                 }
@@ -466,6 +410,10 @@ impl TransactionProcessor {
         for (i, account) in accounts.iter().enumerate() {
             let pre_balance = *pre_balances.get(i).unwrap_or(&0);
             let post_balance = *post_balances.get(i).unwrap_or(&0);
+
+            if let (Some(node_state), true) = (&self.node_state, tracked_addresses.contains(&account.pubkey)) {
+                node_state.record_balance(&account.pubkey, notification.slot, post_balance);
+            }
             
             if pre_balance != post_balance && tracked_addresses.contains(&account.pubkey) {
                 let mpsc_message = LamportsBalanceUpdate {
@@ -487,9 +435,55 @@ impl TransactionProcessor {
             }
         }
 
-        let pre_token_balances = meta.pre_token_balances.clone().unwrap();
-        let post_token_balances = meta.post_token_balances.clone().unwrap();
+        // Providers may omit token balances altogether, which is treated as no balances.
+        let pre_token_balances = match &meta.pre_token_balances {
+            OptionSerializer::Some(balances) => balances.clone(),
+            _ => Vec::new(),
+        };
+        let post_token_balances = match &meta.post_token_balances {
+            OptionSerializer::Some(balances) => balances.clone(),
+            _ => Vec::new(),
+        };
         let token_balance_updates = compile_balance_updates(&pre_token_balances, &post_token_balances);
+
+        if let Some(node_state) = &self.node_state {
+            for balance in &post_token_balances {
+                let owner = match &balance.owner {
+                    OptionSerializer::Some(owner) if tracked_addresses.contains(owner) => owner,
+                    _ => continue,
+                };
+                let account = match accounts.get(balance.account_index as usize) {
+                    Some(account) => account,
+                    None => continue,
+                };
+
+                node_state.record_token_account(TokenAccountState {
+                    address: account.pubkey.clone(),
+                    mint: balance.mint.clone(),
+                    owner: owner.clone(),
+                    program_id: match &balance.program_id {
+                        OptionSerializer::Some(program_id) => program_id.clone(),
+                        _ => TOKEN_PROGRAM_ID.to_string(),
+                    },
+                    amount: balance.ui_token_amount.amount.parse().unwrap_or(0),
+                    decimals: balance.ui_token_amount.decimals,
+                    lamports: *post_balances.get(balance.account_index as usize).unwrap_or(&0),
+                    slot: notification.slot,
+                });
+            }
+
+            // Token accounts closed by the transaction have no post balance.
+            for balance in &pre_token_balances {
+                if !matches!(&balance.owner, OptionSerializer::Some(owner) if tracked_addresses.contains(owner))
+                    || post_token_balances.iter().any(|post| post.account_index == balance.account_index)
+                {
+                    continue;
+                }
+                if let Some(account) = accounts.get(balance.account_index as usize) {
+                    node_state.remove_token_account(&account.pubkey, notification.slot);
+                }
+            }
+        }
         for (key, update) in token_balance_updates {
             // By definition of the transaction pre- and post- token balances,
             // the pre-balance and post-balances will always be different. This means
@@ -518,4 +512,120 @@ impl TransactionProcessor {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use deadpool_redis::{Config, Runtime};
+    use futures::{future::BoxFuture, stream, StreamExt};
+    use solana_transaction_status::{EncodedTransaction, EncodedTransactionWithStatusMeta};
+    use std::{io::Write, sync::atomic::{AtomicUsize, Ordering}, time::Duration};
+    use tokio::net::TcpListener;
+    use tonic::{service::Routes, transport::Server};
+
+    use crate::sources::{recording::RecordedNotification, replay::{ReplaySource, ReplaySpeed}};
+
+    /// Counts the subscriptions to and the notifications yielded by the wrapped source.
+    struct CountingSource {
+        inner: Arc<dyn TransactionSource>,
+        subscriptions: Arc<AtomicUsize>,
+        notifications: Arc<AtomicUsize>,
+    }
+
+    impl TransactionSource for CountingSource {
+        fn name(&self) -> &str {
+            self.inner.name()
+        }
+
+        fn reconnects(&self) -> bool {
+            self.inner.reconnects()
+        }
+
+        fn subscribe(&self) -> BoxFuture<'_, Result<NotificationStream, SourceError>> {
+            Box::pin(async move {
+                self.subscriptions.fetch_add(1, Ordering::SeqCst);
+                let notifications = self.notifications.clone();
+                let stream = self.inner.subscribe().await?.inspect(move |_| {
+                    notifications.fetch_add(1, Ordering::SeqCst);
+                });
+                Ok(Box::pin(stream) as NotificationStream)
+            })
+        }
+    }
+
+    /// The serum market client connects eagerly, an empty gRPC server is enough.
+    async fn serum_market_client() -> SerumMarketClient {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let incoming = stream::unfold(listener, |listener| async move {
+            let connection = listener.accept().await.map(|(stream, _)| stream);
+            Some((connection, listener))
+        });
+
+        tokio::spawn(Server::builder().add_routes(Routes::default()).serve_with_incoming(incoming));
+        SerumMarketClient::connect(&addr.to_string(), "key".to_string(), false).await.unwrap()
+    }
+
+    fn recorded(signature: &str, slot: u64) -> RecordedNotification {
+        RecordedNotification {
+            received_at: slot as i64,
+            frame: None,
+            result: Some(TransactionNotificationResult {
+                transaction: EncodedTransactionWithStatusMeta {
+                    transaction: EncodedTransaction::LegacyBinary(signature.to_string()),
+                    meta: None,
+                    version: None,
+                },
+                signature: signature.to_string(),
+                slot,
+                frame: None,
+            }),
+        }
+    }
+
+    #[tokio::test]
+    async fn replay_is_processed_exactly_once() {
+        let path = std::env::temp_dir().join(format!("replay-{}.jsonl", uuid::Uuid::new_v4()));
+        let signatures: Vec<String> = (0..20).map(|index| format!("signature-{}", index)).collect();
+        let mut file = File::create(&path).unwrap();
+        for (slot, signature) in signatures.iter().enumerate() {
+            writeln!(file, "{}", serde_json::to_string(&recorded(signature, slot as u64)).unwrap()).unwrap();
+        }
+        drop(file);
+
+        let subscriptions = Arc::new(AtomicUsize::new(0));
+        let notifications = Arc::new(AtomicUsize::new(0));
+        let source = CountingSource {
+            inner: Arc::new(ReplaySource::new(path.to_str().unwrap(), ReplaySpeed::Max)),
+            subscriptions: subscriptions.clone(),
+            notifications: notifications.clone(),
+        };
+
+        // Nothing is published, the notifications carry no meta and Redis is unreachable.
+        let redis_pool = Config::from_url("redis://127.0.0.1:1").create_pool(Some(Runtime::Tokio1)).unwrap();
+        let node_state = NodeState::new();
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let processor = TransactionProcessor::new(
+            Arc::new(source),
+            None,
+            None,
+            None,
+            None,
+            Some(node_state.clone()),
+            true,
+            tx,
+            Arc::new(redis_pool),
+            serum_market_client().await
+        ).await.unwrap();
+
+        tokio::time::timeout(Duration::from_secs(10), processor.start_processor()).await
+            .expect("The processor should return once the replay has ended");
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(subscriptions.load(Ordering::SeqCst), 1);
+        assert_eq!(notifications.load(Ordering::SeqCst), signatures.len());
+        for (slot, signature) in signatures.iter().enumerate() {
+            assert_eq!(node_state.signature_status(signature).unwrap().slot, slot as u64);
+        }
+    }
+}
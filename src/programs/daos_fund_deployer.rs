@@ -19,7 +19,7 @@ pub enum DaosFundDeployerFunction {
 
 pub const DAOS_FUND_DEPLOYER: DaosFundDeployer = DaosFundDeployer {
     addresses: DaosFundDeployerAddresses {
-        program_id: "4FqThZWv3QKWkSyXCDmATpWkpEiCHq5yhkdGWpSEDAZM  ",
+        program_id: "4FqThZWv3QKWkSyXCDmATpWkpEiCHq5yhkdGWpSEDAZM",
     },
     discriminators: DaosFundDeployerDiscriminators {
         initialize_curve: 0x265d01d63bb94c59,
@@ -40,4 +40,10 @@ impl DaosFundDeployerFunction {
             _ => None,
         }
     }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DaosFundDeployerFunction::InitializeCurve => "initializeCurve",
+        }
+    }
 }
\ No newline at end of file
@@ -3,6 +3,12 @@ pub mod pump_fun;
 pub mod raydium;
 pub mod serum;
 
+use daos_fund_deployer::DaosFundDeployerFunction;
+use pump_fun::PumpFunFunction;
+use raydium::RaydiumFunction;
+use serum::SerumFunction;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ProgramId {
     DaosFundDeployer,
     PumpFun,
@@ -11,13 +17,89 @@ pub enum ProgramId {
 }
 
 impl ProgramId {
-    pub fn from_str(program_id: &str) -> Option<ProgramId> {
-        match program_id {
-            x if x == daos_fund_deployer::DAOS_FUND_DEPLOYER.addresses.program_id => Some(ProgramId::DaosFundDeployer),
-            x if x == pump_fun::PUMP_FUN.addresses.program_id => Some(ProgramId::PumpFun),
-            x if x == raydium::RAYDIUM.addresses.program_id => Some(ProgramId::Raydium),
-            x if x == serum::SERUM.addresses.program_id => Some(ProgramId::Serum),
-            _ => None,
+    pub const ALL: [ProgramId; 4] = [
+        ProgramId::DaosFundDeployer,
+        ProgramId::PumpFun,
+        ProgramId::Raydium,
+        ProgramId::Serum,
+    ];
+
+    pub fn from_address(program_id: &str) -> Option<ProgramId> {
+        ProgramId::ALL.into_iter().find(|program| program.address() == program_id)
+    }
+
+    pub fn address(&self) -> &'static str {
+        match self {
+            ProgramId::DaosFundDeployer => daos_fund_deployer::DAOS_FUND_DEPLOYER.addresses.program_id,
+            ProgramId::PumpFun => pump_fun::PUMP_FUN.addresses.program_id,
+            ProgramId::Raydium => raydium::RAYDIUM.addresses.program_id,
+            ProgramId::Serum => serum::SERUM.addresses.program_id,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProgramId::DaosFundDeployer => "daos_fund_deployer",
+            ProgramId::PumpFun => "pump_fun",
+            ProgramId::Raydium => "raydium",
+            ProgramId::Serum => "serum",
+        }
+    }
+
+    /// Name of the instruction encoded in the base58 `data`, if it is one we decode.
+    pub fn instruction_name(&self, data: &str) -> Option<&'static str> {
+        match self {
+            ProgramId::DaosFundDeployer => DaosFundDeployerFunction::from_data(data).map(|function| function.name()),
+            ProgramId::PumpFun => PumpFunFunction::from_data(data).map(|function| function.name()),
+            ProgramId::Raydium => RaydiumFunction::from_data(data).map(|function| function.name()),
+            ProgramId::Serum => SerumFunction::from_data(data).map(|function| function.name()),
+        }
+    }
+
+    /// Maps a `Custom` instruction error code to the name it has in the program's error table.
+    pub fn error_name(&self, code: u32) -> Option<&'static str> {
+        match self {
+            ProgramId::DaosFundDeployer => anchor_error_name(code),
+            ProgramId::PumpFun => pump_fun::error_name(code).or_else(|| anchor_error_name(code)),
+            ProgramId::Raydium => raydium::error_name(code),
+            ProgramId::Serum => None,
         }
     }
+}
+
+/// Errors raised by the Anchor framework itself, shared by every Anchor program.
+/// https://github.com/coral-xyz/anchor/blob/master/lang/src/error.rs
+fn anchor_error_name(code: u32) -> Option<&'static str> {
+    match code {
+        100 => Some("InstructionMissing"),
+        101 => Some("InstructionFallbackNotFound"),
+        102 => Some("InstructionDidNotDeserialize"),
+        103 => Some("InstructionDidNotSerialize"),
+        2000 => Some("ConstraintMut"),
+        2001 => Some("ConstraintHasOne"),
+        2002 => Some("ConstraintSigner"),
+        2003 => Some("ConstraintRaw"),
+        2004 => Some("ConstraintOwner"),
+        2005 => Some("ConstraintRentExempt"),
+        2006 => Some("ConstraintSeeds"),
+        2012 => Some("ConstraintAddress"),
+        2014 => Some("ConstraintTokenMint"),
+        2015 => Some("ConstraintTokenOwner"),
+        3000 => Some("AccountDiscriminatorAlreadySet"),
+        3001 => Some("AccountDiscriminatorNotFound"),
+        3002 => Some("AccountDiscriminatorMismatch"),
+        3003 => Some("AccountDidNotDeserialize"),
+        3004 => Some("AccountDidNotSerialize"),
+        3005 => Some("AccountNotEnoughKeys"),
+        3006 => Some("AccountNotMutable"),
+        3007 => Some("AccountOwnedByWrongProgram"),
+        3008 => Some("InvalidProgramId"),
+        3009 => Some("InvalidProgramExecutable"),
+        3010 => Some("AccountNotSigner"),
+        3011 => Some("AccountNotSystemOwned"),
+        3012 => Some("AccountNotInitialized"),
+        3013 => Some("AccountNotProgramData"),
+        3014 => Some("AccountNotAssociatedTokenAccount"),
+        _ => None,
+    }
 }
\ No newline at end of file
@@ -1,3 +1,6 @@
+use borsh::BorshDeserialize;
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
 use bs58;
 
 pub struct PumpFunAddresses {
@@ -8,6 +11,7 @@ pub struct PumpFunDiscriminators {
     pub creation: u64,
     pub buy: u64,
     pub sell: u64,
+    pub bonding_curve_account: u64,
 }
 
 pub struct PumpFun {
@@ -29,6 +33,7 @@ pub const PUMP_FUN: PumpFun = PumpFun {
         creation: 0x181ec828051c0777,
         buy: 0x66063d1201daebea,
         sell: 0x33e685a4017f83ad,
+        bonding_curve_account: 0x17b7f83760d8ac60,
     },
 };
 
@@ -48,4 +53,144 @@ impl PumpFunFunction {
             _ => None,
         }
     }
-}
\ No newline at end of file
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PumpFunFunction::Creation => "create",
+            PumpFunFunction::Buy => "buy",
+            PumpFunFunction::Sell => "sell",
+        }
+    }
+}
+
+/// Custom errors from the `pump.fun` IDL, Anchor numbers them from 6000 onwards.
+pub fn error_name(code: u32) -> Option<&'static str> {
+    match code {
+        6000 => Some("NotAuthorized"),
+        6001 => Some("AlreadyInitialized"),
+        6002 => Some("TooMuchSolRequired"),
+        6003 => Some("TooLittleSolReceived"),
+        6004 => Some("MintDoesNotMatchBondingCurve"),
+        6005 => Some("BondingCurveComplete"),
+        6006 => Some("BondingCurveNotComplete"),
+        6007 => Some("NotInitialized"),
+        _ => None,
+    }
+}
+
+/// On-chain state of a token's bonding curve. Newer accounts append further fields (e.g. the
+/// creator), which are ignored.
+#[derive(Debug, Clone, BorshDeserialize)]
+pub struct BondingCurve {
+    pub virtual_token_reserves: u64,
+    pub virtual_sol_reserves: u64,
+    pub real_token_reserves: u64,
+    pub real_sol_reserves: u64,
+    pub token_total_supply: u64,
+    pub complete: bool,
+}
+
+impl BondingCurve {
+    /// Decodes the raw account data, returning `None` for any other account type.
+    pub fn from_account_data(data: &[u8]) -> Option<BondingCurve> {
+        if data.len() < 8 {
+            return None;
+        }
+
+        let discriminator = u64::from_be_bytes(data[0..8].try_into().unwrap());
+        if discriminator != PUMP_FUN.discriminators.bonding_curve_account {
+            return None;
+        }
+
+        BondingCurve::deserialize(&mut &data[8..]).ok()
+    }
+
+    /// The bonding curve account of `mint`, derived from the `["bonding-curve", mint]` seeds.
+    pub fn address(mint: &str) -> Option<String> {
+        let mint = Pubkey::from_str(mint).ok()?;
+        let program_id = Pubkey::from_str(PUMP_FUN.addresses.program_id).ok()?;
+        let (address, _bump) = Pubkey::find_program_address(&[b"bonding-curve", mint.as_ref()], &program_id);
+
+        Some(address.to_string())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Account data as stored on chain: the discriminator followed by the Borsh encoded fields.
+    fn bonding_curve_data(complete: u8) -> Vec<u8> {
+        let mut data = PUMP_FUN.discriminators.bonding_curve_account.to_be_bytes().to_vec();
+        for value in [1_073_000_000_000_000u64, 30_000_000_000, 793_100_000_000_000, 0, 1_000_000_000_000_000] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.push(complete);
+        data
+    }
+
+    #[test]
+    fn discriminator_matches_the_anchor_account_name() {
+        let hash = solana_sdk::hash::hash(b"account:BondingCurve");
+        assert_eq!(
+            PUMP_FUN.discriminators.bonding_curve_account.to_be_bytes(),
+            hash.to_bytes()[..8]
+        );
+    }
+
+    #[test]
+    fn decodes_the_borsh_layout() {
+        let bonding_curve = BondingCurve::from_account_data(&bonding_curve_data(1)).unwrap();
+
+        assert_eq!(bonding_curve.virtual_token_reserves, 1_073_000_000_000_000);
+        assert_eq!(bonding_curve.virtual_sol_reserves, 30_000_000_000);
+        assert_eq!(bonding_curve.real_token_reserves, 793_100_000_000_000);
+        assert_eq!(bonding_curve.real_sol_reserves, 0);
+        assert_eq!(bonding_curve.token_total_supply, 1_000_000_000_000_000);
+        assert!(bonding_curve.complete);
+    }
+
+    #[test]
+    fn ignores_appended_fields() {
+        let mut data = bonding_curve_data(0);
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+
+        let bonding_curve = BondingCurve::from_account_data(&data).unwrap();
+        assert_eq!(bonding_curve.token_total_supply, 1_000_000_000_000_000);
+        assert!(!bonding_curve.complete);
+    }
+
+    #[test]
+    fn rejects_other_accounts_and_malformed_data() {
+        let mut other = bonding_curve_data(0);
+        other[0] ^= 0xff;
+        assert!(BondingCurve::from_account_data(&other).is_none());
+
+        assert!(BondingCurve::from_account_data(&[0x17, 0xb7, 0xf8]).is_none());
+
+        let data = bonding_curve_data(0);
+        assert!(BondingCurve::from_account_data(&data[..data.len() - 2]).is_none());
+        assert!(BondingCurve::from_account_data(&bonding_curve_data(2)).is_none());
+    }
+
+    #[test]
+    fn derives_the_bonding_curve_address() {
+        let mint = Pubkey::new_unique();
+        let program_id = Pubkey::from_str(PUMP_FUN.addresses.program_id).unwrap();
+        let address = BondingCurve::address(&mint.to_string()).unwrap();
+
+        let (expected, _bump) = Pubkey::find_program_address(&[b"bonding-curve", mint.as_ref()], &program_id);
+        assert_eq!(address, expected.to_string());
+        assert!(!Pubkey::from_str(&address).unwrap().is_on_curve());
+        assert!(BondingCurve::address("not a mint").is_none());
+    }
+
+    #[test]
+    fn classifies_instruction_data() {
+        let data = |discriminator: u64| bs58::encode(discriminator.to_be_bytes()).into_string();
+
+        assert!(matches!(PumpFunFunction::from_data(&data(PUMP_FUN.discriminators.buy)), Some(PumpFunFunction::Buy)));
+        assert!(matches!(PumpFunFunction::from_data(&data(PUMP_FUN.discriminators.sell)), Some(PumpFunFunction::Sell)));
+        assert!(matches!(PumpFunFunction::from_data(&data(PUMP_FUN.discriminators.creation)), Some(PumpFunFunction::Creation)));
+        assert!(PumpFunFunction::from_data(&data(PUMP_FUN.discriminators.bonding_curve_account)).is_none());
+    }
+}
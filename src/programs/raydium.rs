@@ -33,7 +33,7 @@ impl RaydiumFunction {
     pub fn from_data(data: &str) -> Option<RaydiumFunction> {
         let bytes = bs58::decode(data).into_vec().ok()?;
 
-        if data.len() < 1 {
+        if data.is_empty() {
             return None;
         }
 
@@ -44,4 +44,50 @@ impl RaydiumFunction {
             _ => None,
         }
     }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RaydiumFunction::Initialize => "initialize",
+            RaydiumFunction::Initialize2 => "initialize2",
+        }
+    }
+}
+
+/// `AmmError` from the Raydium AMM v4 program.
+/// https://github.com/raydium-io/raydium-amm/blob/master/program/src/error.rs
+pub fn error_name(code: u32) -> Option<&'static str> {
+    match code {
+        0 => Some("AlreadyInUse"),
+        1 => Some("InvalidProgramAddress"),
+        2 => Some("ExpectedMint"),
+        3 => Some("ExpectedAccount"),
+        4 => Some("InvalidCoinVault"),
+        5 => Some("InvalidPCVault"),
+        6 => Some("InvalidTokenLP"),
+        7 => Some("InvalidDestTokenCoin"),
+        8 => Some("InvalidDestTokenPC"),
+        9 => Some("InvalidPoolMint"),
+        10 => Some("InvalidOpenOrders"),
+        11 => Some("InvalidSerumMarket"),
+        12 => Some("InvalidSerumProgram"),
+        13 => Some("InvalidTargetOrders"),
+        14 => Some("InvalidWithdrawQueue"),
+        15 => Some("InvalidTempLp"),
+        16 => Some("InvalidCoinMint"),
+        17 => Some("InvalidPCMint"),
+        18 => Some("InvalidOwner"),
+        19 => Some("InvalidSupply"),
+        20 => Some("InvalidDelegate"),
+        21 => Some("InvalidSignAccount"),
+        22 => Some("InvalidStatus"),
+        23 => Some("InvalidInstruction"),
+        24 => Some("WrongAccountsNumber"),
+        25 => Some("WithdrawTransferBusy"),
+        26 => Some("WithdrawQueueFull"),
+        27 => Some("WithdrawQueueEmpty"),
+        28 => Some("InvalidParamsSet"),
+        29 => Some("InvalidInput"),
+        30 => Some("ExceededSlippage"),
+        _ => None,
+    }
 }
\ No newline at end of file
@@ -46,4 +46,10 @@ impl SerumFunction {
             _ => None,
         }
     }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SerumFunction::InitializeMarket => "initializeMarket",
+        }
+    }
 }
\ No newline at end of file
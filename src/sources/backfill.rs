@@ -0,0 +1,308 @@
+/// # Gap Backfill
+/// Whenever a source reconnects, every transaction that landed while it was disconnected is
+/// lost. The backfiller recovers them through a standard RPC node: `getSignaturesForAddress`
+/// is paged back to the last processed slot for each tracked program in `ProgramId`, and the
+/// resulting signatures are fetched with `getTransaction` in the `jsonParsed` encoding.
+
+use futures::{stream, StreamExt};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use tracing::{info, warn};
+
+use crate::helpers::json_rpc::JsonRpcClient;
+use crate::programs::ProgramId;
+
+use super::{rpc::get_transaction, SourceError, TransactionNotificationResult};
+
+/// Maximum number of signatures returned by a single `getSignaturesForAddress` request.
+const SIGNATURES_PAGE_LIMIT: usize = 1000;
+
+/// Number of `getTransaction` requests kept in flight while backfilling.
+const CONCURRENT_REQUESTS: usize = 8;
+
+#[derive(Debug, Deserialize)]
+struct SignatureInfo {
+    signature: String,
+    slot: u64,
+    err: Option<Value>,
+}
+
+pub struct Backfiller {
+    rpc_client: JsonRpcClient,
+    max_pages: usize,
+    include_failed: bool,
+}
+
+impl Backfiller {
+    /// `max_pages` bounds the amount of signatures fetched per program, busy programs such as
+    /// pump.fun can otherwise turn a long outage into an enormous backfill.
+    pub fn new(rpc_url: &str, max_pages: usize, include_failed: bool) -> Self {
+        Self {
+            rpc_client: JsonRpcClient::new(rpc_url),
+            max_pages,
+            include_failed,
+        }
+    }
+
+    /// Returns every transaction of the tracked programs from `from_slot` (inclusive) onwards,
+    /// ordered by slot. Failed transactions are skipped unless `include_failed` is set.
+    pub async fn backfill(&self, from_slot: u64) -> Result<Vec<TransactionNotificationResult>, SourceError> {
+        let mut signatures: Vec<SignatureInfo> = Vec::new();
+        let mut seen: HashSet<String> = HashSet::new();
+
+        for program in ProgramId::ALL {
+            for info in self.signatures_since(program.address(), from_slot).await? {
+                if seen.insert(info.signature.clone()) {
+                    signatures.push(info);
+                }
+            }
+        }
+
+        // `getSignaturesForAddress` returns the newest signatures first, the handlers need
+        // them in the order they landed on chain.
+        signatures.sort_by_key(|info| info.slot);
+
+        info!("Backfilling {} transaction(s) since slot {}", signatures.len(), from_slot);
+
+        let results: Vec<Option<TransactionNotificationResult>> = stream::iter(signatures)
+            .map(|info| async move {
+                match get_transaction(&self.rpc_client, &info.signature).await {
+                    Ok(Some(confirmed)) => Some(TransactionNotificationResult {
+                        transaction: confirmed.transaction,
+                        signature: info.signature,
+                        slot: confirmed.slot,
+                        frame: None,
+                    }),
+                    Ok(None) => {
+                        warn!("Backfill transaction {} could not be retrieved, skipping.", info.signature);
+                        None
+                    }
+                    Err(err) => {
+                        warn!("Failed to backfill transaction {}: {}", info.signature, err);
+                        None
+                    }
+                }
+            })
+            .buffered(CONCURRENT_REQUESTS)
+            .collect()
+            .await;
+
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    async fn signatures_since(&self, address: &str, from_slot: u64) -> Result<Vec<SignatureInfo>, SourceError> {
+        let mut signatures = Vec::new();
+        let mut before: Option<String> = None;
+
+        for page in 0..self.max_pages {
+            let mut config = json!({
+                "commitment": "confirmed",
+                "limit": SIGNATURES_PAGE_LIMIT,
+            });
+            if let Some(before) = &before {
+                config["before"] = json!(before);
+            }
+
+            let infos: Vec<SignatureInfo> = self.rpc_client
+                .call("getSignaturesForAddress", json!([address, config]))
+                .await?;
+
+            let exhausted = infos.len() < SIGNATURES_PAGE_LIMIT;
+            let mut reached_slot = false;
+            before = infos.last().map(|info| info.signature.clone());
+
+            for info in infos {
+                if info.slot < from_slot {
+                    reached_slot = true;
+                    break;
+                }
+                if self.include_failed || info.err.is_none() {
+                    signatures.push(info);
+                }
+            }
+
+            if reached_slot || exhausted {
+                break;
+            }
+
+            if page + 1 == self.max_pages {
+                warn!(
+                    "Backfill for {} stopped after {} page(s) before reaching slot {}, some transactions are missing.",
+                    address,
+                    self.max_pages,
+                    from_slot
+                );
+            }
+        }
+
+        Ok(signatures)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, routing::post, Json, Router};
+    use futures::{future::BoxFuture, stream::BoxStream};
+    use solana_transaction_status::{
+        EncodedConfirmedTransactionWithStatusMeta,
+        EncodedTransaction,
+        EncodedTransactionWithStatusMeta
+    };
+    use std::{collections::{HashMap, VecDeque}, sync::{Arc, Mutex}, time::Duration};
+    use tokio::net::TcpListener;
+
+    use crate::sources::{multi::MultiSource, NotificationStream, TransactionSource};
+
+    /// Serves `getSignaturesForAddress` and `getTransaction` from a fixed set of signatures.
+    #[derive(Default)]
+    struct MockRpc {
+        /// `(signature, slot, failed)` of every program, newest first.
+        signatures: HashMap<&'static str, Vec<(&'static str, u64, bool)>>,
+        fetched: Mutex<Vec<String>>,
+    }
+
+    impl MockRpc {
+        fn slot_of(&self, signature: &str) -> Option<u64> {
+            self.signatures.values().flatten().find(|(other, _, _)| *other == signature).map(|(_, slot, _)| *slot)
+        }
+    }
+
+    async fn handle_request(State(rpc): State<Arc<MockRpc>>, Json(request): Json<Value>) -> Json<Value> {
+        let params = &request["params"];
+        let result = match request["method"].as_str().unwrap() {
+            "getSignaturesForAddress" => {
+                let signatures = rpc.signatures.get(params[0].as_str().unwrap()).cloned().unwrap_or_default();
+                let limit = params[1]["limit"].as_u64().unwrap() as usize;
+                let skip = match params[1]["before"].as_str() {
+                    Some(before) => signatures.iter().position(|(signature, _, _)| *signature == before).unwrap() + 1,
+                    None => 0,
+                };
+
+                json!(signatures.iter().skip(skip).take(limit).map(|(signature, slot, failed)| json!({
+                    "signature": signature,
+                    "slot": slot,
+                    "err": if *failed { json!({ "InstructionError": [0, { "Custom": 1 }] }) } else { Value::Null },
+                })).collect::<Vec<Value>>())
+            }
+            "getTransaction" => {
+                let signature = params[0].as_str().unwrap();
+                rpc.fetched.lock().unwrap().push(signature.to_string());
+                json!(rpc.slot_of(signature).map(|slot| transaction(signature, slot)))
+            }
+            method => panic!("Unexpected method {}", method),
+        };
+
+        Json(json!({ "jsonrpc": "2.0", "result": result, "id": request["id"] }))
+    }
+
+    fn transaction(signature: &str, slot: u64) -> EncodedConfirmedTransactionWithStatusMeta {
+        EncodedConfirmedTransactionWithStatusMeta {
+            slot,
+            transaction: EncodedTransactionWithStatusMeta {
+                transaction: EncodedTransaction::LegacyBinary(signature.to_string()),
+                meta: None,
+                version: None,
+            },
+            block_time: None,
+        }
+    }
+
+    fn notification(signature: &str, slot: u64) -> TransactionNotificationResult {
+        TransactionNotificationResult {
+            transaction: transaction(signature, slot).transaction,
+            signature: signature.to_string(),
+            slot,
+            frame: None,
+        }
+    }
+
+    async fn serve(rpc: Arc<MockRpc>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let app = Router::new().route("/", post(handle_request)).with_state(rpc);
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url
+    }
+
+    /// A transaction touching two tracked programs, a failed one and one from before the gap.
+    fn mock_rpc() -> Arc<MockRpc> {
+        let mut rpc = MockRpc::default();
+        rpc.signatures.insert(ProgramId::PumpFun.address(), vec![
+            ("both", 104, false),
+            ("failed", 103, true),
+            ("pump", 102, false),
+            ("before-gap", 99, false),
+        ]);
+        rpc.signatures.insert(ProgramId::Raydium.address(), vec![
+            ("both", 104, false),
+            ("raydium", 101, false),
+        ]);
+        rpc.signatures.insert(ProgramId::DaosFundDeployer.address(), vec![
+            ("daos", 100, false),
+        ]);
+        Arc::new(rpc)
+    }
+
+    fn signatures(notifications: &[TransactionNotificationResult]) -> Vec<&str> {
+        notifications.iter().map(|notification| notification.signature.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn backfills_gap_in_slot_order_without_duplicates() {
+        let rpc = mock_rpc();
+        let backfiller = Backfiller::new(&serve(rpc.clone()).await, 5, false);
+
+        let notifications = backfiller.backfill(100).await.unwrap();
+
+        assert_eq!(signatures(&notifications), vec!["daos", "raydium", "pump", "both"]);
+        assert_eq!(notifications.iter().map(|notification| notification.slot).collect::<Vec<u64>>(), vec![100, 101, 102, 104]);
+
+        let mut fetched = rpc.fetched.lock().unwrap().clone();
+        fetched.sort();
+        assert_eq!(fetched, vec!["both", "daos", "pump", "raydium"]);
+    }
+
+    /// Yields one scripted stream per subscription, then stays silent.
+    struct ScriptedSource {
+        scripts: Mutex<VecDeque<Vec<Result<TransactionNotificationResult, SourceError>>>>,
+    }
+
+    impl TransactionSource for ScriptedSource {
+        fn name(&self) -> &str {
+            "scripted"
+        }
+
+        fn subscribe(&self) -> BoxFuture<'_, Result<NotificationStream, SourceError>> {
+            let script = self.scripts.lock().unwrap().pop_front();
+            Box::pin(async move {
+                let stream: BoxStream<'static, _> = match script {
+                    Some(script) => Box::pin(stream::iter(script).chain(stream::pending())),
+                    None => Box::pin(stream::pending()),
+                };
+                Ok(stream)
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn multi_source_backfills_reconnect_gap_without_duplicates() {
+        let backfiller = Backfiller::new(&serve(mock_rpc()).await, 5, false);
+        let source = ScriptedSource {
+            scripts: Mutex::new(VecDeque::from(vec![
+                vec![Ok(notification("live", 100)), Err("connection lost".into())],
+                vec![Ok(notification("both", 104)), Ok(notification("after-gap", 105))],
+            ])),
+        };
+
+        let multi = MultiSource::new(vec![Arc::new(source)], Some(Arc::new(backfiller)));
+        let stream = multi.subscribe().await.unwrap();
+        let notifications: Vec<TransactionNotificationResult> = tokio::time::timeout(
+            Duration::from_secs(5),
+            stream.take(6).map(Result::unwrap).collect()
+        ).await.unwrap();
+
+        assert_eq!(signatures(&notifications), vec!["live", "daos", "raydium", "pump", "both", "after-gap"]);
+    }
+}
@@ -0,0 +1,170 @@
+/// # Subscription Filters
+/// Builds the account filter sent to providers that support server-side filtering. In the
+/// `Narrow` mode only transactions touching a tracked program in `ProgramId`, a tracked user
+/// or a tracked token are streamed, which cuts bandwidth and CPU usage by orders of magnitude
+/// compared to the `Firehose` of every non-vote transaction on chain.
+///
+/// A program invoked through CPI must still be part of the transaction's account keys, so the
+/// program handlers see every transaction they would see in the `Firehose`. SPL token balance
+/// updates are not: a transfer into a tracked user's token account does not necessarily
+/// include the owner's wallet in its account keys, so `Narrow` misses those updates. Use the
+/// `Firehose` when every `SPL_TOKEN_BALANCE_UPDATE` is needed.
+
+use deadpool_redis::Pool;
+use redis::AsyncCommands;
+use futures::StreamExt;
+use tokio::sync::watch;
+use std::{collections::BTreeSet, sync::Arc, time::Duration};
+use tracing::{info, warn};
+
+use crate::constants::redis::{TRACKED_ADDRESSES_CHANGED_CHANNEL, TRACKED_TOKEN_ADDRESSES, TRACKED_USER_ADDRESSES};
+use crate::programs::ProgramId;
+
+use super::SourceError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterMode {
+    Narrow,
+    Firehose,
+}
+
+impl FilterMode {
+    pub fn from_name(mode: &str) -> Option<FilterMode> {
+        match mode.to_lowercase().as_str() {
+            "narrow" => Some(FilterMode::Narrow),
+            "firehose" => Some(FilterMode::Firehose),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SubscriptionFilter {
+    mode: FilterMode,
+    include_failed: bool,
+    redis_pool: Arc<Pool>,
+    changes: Option<watch::Receiver<u64>>,
+}
+
+impl SubscriptionFilter {
+    /// `tracked_sets` is the version bumped by `watch_tracked_sets`, only followed in narrow
+    /// mode since a firehose subscription never needs to change.
+    pub fn new(mode: FilterMode, include_failed: bool, redis_pool: Arc<Pool>, tracked_sets: watch::Receiver<u64>) -> Self {
+        let changes = match mode {
+            FilterMode::Narrow => Some(tracked_sets),
+            FilterMode::Firehose => None,
+        };
+
+        Self { mode, include_failed, redis_pool, changes }
+    }
+
+    /// Whether transactions that failed on chain should be streamed as well.
+    pub fn include_failed(&self) -> bool {
+        self.include_failed
+    }
+
+    /// Change notifications for a single connection, starting from the current state. Must be
+    /// created before `account_include` is read, so that a change in between is not missed.
+    pub fn changes(&self) -> FilterChanges {
+        FilterChanges::new(self.changes.clone())
+    }
+
+    /// The `accountInclude` list for the current state of the tracked sets, sorted so that two
+    /// filters can be compared. Empty in firehose mode, which providers treat as "everything".
+    pub async fn account_include(&self) -> Result<Vec<String>, SourceError> {
+        if self.mode == FilterMode::Firehose {
+            return Ok(Vec::new());
+        }
+
+        let mut conn = self.redis_pool.get().await?;
+        let tracked_users: Vec<String> = conn.smembers(TRACKED_USER_ADDRESSES).await?;
+        let tracked_tokens: Vec<String> = conn.smembers(TRACKED_TOKEN_ADDRESSES).await?;
+
+        let accounts: BTreeSet<String> = ProgramId::ALL.iter()
+            .map(|program| program.address().to_string())
+            .chain(tracked_users)
+            .chain(tracked_tokens)
+            .collect();
+
+        Ok(accounts.into_iter().collect())
+    }
+}
+
+/// Bursts of changes (e.g. a script adding many wallets) are coalesced into a single
+/// notification by waiting this long after the first change.
+const CHANGE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+pub struct FilterChanges {
+    receiver: Option<watch::Receiver<u64>>,
+}
+
+impl FilterChanges {
+    /// Follows `tracked_sets` from its current version onwards, never resolves when `None`.
+    pub fn new(tracked_sets: Option<watch::Receiver<u64>>) -> Self {
+        Self {
+            receiver: tracked_sets.map(|mut receiver| {
+                receiver.borrow_and_update();
+                receiver
+            }),
+        }
+    }
+
+    /// Resolves once the tracked sets have changed. Never resolves in firehose mode.
+    pub async fn changed(&mut self) {
+        let receiver = match self.receiver.as_mut() {
+            Some(receiver) => receiver,
+            None => return futures::future::pending().await,
+        };
+
+        if receiver.changed().await.is_err() {
+            self.receiver = None;
+            return futures::future::pending().await;
+        }
+
+        tokio::time::sleep(CHANGE_DEBOUNCE).await;
+        receiver.mark_unchanged();
+    }
+}
+
+/// Watches the tracked sets through Redis keyspace notifications as well as the
+/// `TRACKED_ADDRESSES_CHANGED_CHANNEL` control channel, bumping the returned version on every
+/// change. Keyspace notifications require `notify-keyspace-events` to include `Ks`.
+///
+/// A single watcher is shared by every consumer, it stops once all receivers are dropped.
+pub fn watch_tracked_sets(redis_url: &str) -> watch::Receiver<u64> {
+    let redis_url = redis_url.to_string();
+    let (sender, receiver) = watch::channel(0u64);
+
+    tokio::spawn(async move {
+        while !sender.is_closed() {
+            if let Err(err) = forward_set_changes(&redis_url, &sender).await {
+                warn!("Tracked set watcher failed: {}. Retrying in 1 second(s)...", err);
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    });
+
+    receiver
+}
+
+async fn forward_set_changes(redis_url: &str, sender: &watch::Sender<u64>) -> Result<(), SourceError> {
+    let client = redis::Client::open(redis_url)?;
+    let mut pubsub = client.get_async_pubsub().await?;
+
+    pubsub.subscribe(TRACKED_ADDRESSES_CHANGED_CHANNEL).await?;
+    for key in [TRACKED_USER_ADDRESSES, TRACKED_TOKEN_ADDRESSES] {
+        pubsub.psubscribe(format!("__keyspace@*__:{}", key)).await?;
+    }
+    info!("Watching tracked sets for changes.");
+
+    let mut messages = pubsub.on_message();
+    loop {
+        tokio::select! {
+            message = messages.next() => match message {
+                Some(_) => sender.send_modify(|version| *version += 1),
+                None => return Err("Redis pub/sub connection closed".into()),
+            },
+            _ = sender.closed() => return Ok(()),
+        }
+    }
+}
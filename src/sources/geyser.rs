@@ -0,0 +1,578 @@
+/// # Yellowstone Geyser Transaction Source
+/// Streams transactions from a Yellowstone (Dragon's Mouth) geyser gRPC `Subscribe` stream.
+/// Geyser pushes transactions straight from the validator, which gives us noticeably lower
+/// latency than the Helius `transactionSubscribe` websocket.
+///
+/// The protobuf transactions are converted into the `solana_sdk` representation and then
+/// encoded as `jsonParsed`, so the instruction handlers receive exactly the same structures
+/// as they would from an RPC provider.
+///
+/// https://github.com/rpcpool/yellowstone-grpc
+
+use solana_sdk::{
+    hash::Hash,
+    instruction::CompiledInstruction,
+    message::{
+        v0::{self, LoadedAddresses, MessageAddressTableLookup},
+        Message as LegacyMessage,
+        MessageHeader,
+        VersionedMessage
+    },
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::{TransactionError, VersionedTransaction},
+    transaction_context::TransactionReturnData
+};
+use solana_transaction_status::{
+    InnerInstruction,
+    InnerInstructions,
+    TransactionStatusMeta,
+    TransactionTokenBalance,
+    UiTransactionEncoding,
+    VersionedTransactionWithStatusMeta
+};
+use solana_account_decoder_client_types::token::UiTokenAmount;
+use tonic::{
+    metadata::AsciiMetadataValue,
+    service::{interceptor::InterceptedService, Interceptor},
+    transport::{Channel, ClientTlsConfig, Endpoint},
+    Request,
+    Status
+};
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use futures::{future::BoxFuture, StreamExt};
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+use super::{filter::SubscriptionFilter, NotificationStream, SourceError, TransactionNotificationResult, TransactionSource};
+
+pub mod solana {
+    pub mod storage {
+        pub mod confirmed_block {
+            tonic::include_proto!("solana.storage.confirmed_block");
+        }
+    }
+}
+
+#[allow(clippy::large_enum_variant)]
+pub mod yellowstone {
+    tonic::include_proto!("geyser");
+}
+
+use solana::storage::confirmed_block as proto;
+use yellowstone::{
+    geyser_client::GeyserClient,
+    subscribe_update::UpdateOneof,
+    CommitmentLevel,
+    SubscribeRequest,
+    SubscribeRequestFilterTransactions,
+    SubscribeRequestPing,
+    SubscribeUpdateTransaction
+};
+
+const FILTER_NAME: &str = "helius_node_emulator";
+
+type AuthenticatedGeyserClient = GeyserClient<InterceptedService<Channel, TokenInterceptor>>;
+
+/// Attaches the `x-token` authentication header expected by most geyser providers.
+#[derive(Clone)]
+struct TokenInterceptor {
+    x_token: Option<AsciiMetadataValue>,
+}
+
+impl Interceptor for TokenInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        if let Some(x_token) = &self.x_token {
+            request.metadata_mut().insert("x-token", x_token.clone());
+        }
+        Ok(request)
+    }
+}
+
+#[derive(Clone)]
+pub struct GeyserSource {
+    name: String,
+    endpoint: String,
+    x_token: Option<String>,
+    filter: SubscriptionFilter,
+}
+
+impl GeyserSource {
+    pub fn new(endpoint: &str, x_token: Option<String>, filter: SubscriptionFilter) -> Self {
+        Self {
+            name: format!("geyser({})", endpoint),
+            endpoint: endpoint.to_string(),
+            x_token,
+            filter,
+        }
+    }
+
+    fn subscribe_request(&self, account_include: Vec<String>) -> SubscribeRequest {
+        info!("Subscribing with {} included account(s).", account_include.len());
+
+        let mut transactions = HashMap::new();
+        transactions.insert(FILTER_NAME.to_string(), SubscribeRequestFilterTransactions {
+            vote: Some(false),
+            // `None` streams both successful and failed transactions.
+            failed: if self.filter.include_failed() { None } else { Some(false) },
+            account_include,
+            account_exclude: vec![],
+            account_required: vec![],
+            signature: None,
+        });
+
+        SubscribeRequest {
+            transactions,
+            commitment: Some(CommitmentLevel::Processed as i32),
+            ..Default::default()
+        }
+    }
+
+    async fn connect(&self) -> Result<AuthenticatedGeyserClient, SourceError> {
+        let mut endpoint = Endpoint::from_shared(self.endpoint.clone())?;
+        if self.endpoint.starts_with("https://") {
+            endpoint = endpoint.tls_config(ClientTlsConfig::new().with_native_roots())?;
+        }
+
+        info!("Connecting to: {}", self.endpoint);
+        let channel = endpoint.connect().await?;
+        info!("gRPC connection established!");
+
+        let x_token = match &self.x_token {
+            Some(token) => Some(token.parse::<AsciiMetadataValue>()?),
+            None => None,
+        };
+
+        Ok(GeyserClient::with_interceptor(channel, TokenInterceptor { x_token })
+            .max_decoding_message_size(64 * 1024 * 1024))
+    }
+}
+
+impl TransactionSource for GeyserSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn subscribe(&self) -> BoxFuture<'_, Result<NotificationStream, SourceError>> {
+        Box::pin(async move {
+            let mut client = self.connect().await?;
+
+            // The request stream must stay open for the lifetime of the subscription, it is
+            // also used to answer the server's keep-alive pings.
+            let (request_sender, request_receiver) = mpsc::unbounded_channel::<SubscribeRequest>();
+            let mut changes = self.filter.changes();
+            let mut active_filter = self.filter.account_include().await?;
+            request_sender.send(self.subscribe_request(active_filter.clone()))?;
+
+            let mut updates = client
+                .subscribe(UnboundedReceiverStream::new(request_receiver))
+                .await?
+                .into_inner();
+            info!("Subscription to geyser transactions successfully sent.");
+
+            let (sender, receiver) = mpsc::unbounded_channel();
+            let this = self.clone();
+            tokio::spawn(async move {
+                loop {
+                    let update = tokio::select! {
+                        update = updates.next() => update,
+                        _ = changes.changed() => {
+                            // Geyser replaces the filters of an existing stream atomically when
+                            // a new request is sent, so there is no gap while resubscribing.
+                            match this.filter.account_include().await {
+                                Ok(account_include) if account_include != active_filter => {
+                                    info!("Tracked accounts changed, updating geyser subscription...");
+                                    let _ = request_sender.send(this.subscribe_request(account_include.clone()));
+                                    active_filter = account_include;
+                                }
+                                Ok(_) => {}
+                                Err(err) => warn!("Failed to build geyser subscription: {}", err),
+                            }
+                            continue;
+                        }
+                    };
+
+                    let update = match update {
+                        Some(Ok(update)) => update,
+                        Some(Err(status)) => {
+                            warn!("Error processing geyser updates: {}", status);
+                            let _ = sender.send(Err(status.into()));
+                            break;
+                        }
+                        None => break,
+                    };
+
+                    match update.update_oneof {
+                        Some(UpdateOneof::Transaction(transaction)) => forward_transaction(transaction, &sender),
+                        Some(UpdateOneof::Ping(_)) => {
+                            let _ = request_sender.send(SubscribeRequest {
+                                ping: Some(SubscribeRequestPing { id: 1 }),
+                                ..Default::default()
+                            });
+                        }
+                        _ => {}
+                    }
+
+                    if sender.is_closed() {
+                        break;
+                    }
+                }
+            });
+
+            Ok(Box::pin(UnboundedReceiverStream::new(receiver)) as NotificationStream)
+        })
+    }
+}
+
+fn forward_transaction(
+    update: SubscribeUpdateTransaction,
+    sender: &UnboundedSender<Result<TransactionNotificationResult, SourceError>>
+) {
+    match convert_transaction(update) {
+        Ok(notification) => {
+            let _ = sender.send(Ok(notification));
+        }
+        Err(err) => warn!("Failed to convert geyser transaction: {}", err),
+    }
+}
+
+/// Converts a geyser transaction update into the normalized `jsonParsed` notification.
+pub fn convert_transaction(update: SubscribeUpdateTransaction) -> Result<TransactionNotificationResult, SourceError> {
+    let info = update.transaction.ok_or("Transaction update is missing its transaction info")?;
+    let transaction = info.transaction.ok_or("Transaction update is missing its transaction")?;
+    let meta = info.meta.ok_or("Transaction update is missing its meta")?;
+
+    let signature = Signature::try_from(info.signature.as_slice())
+        .map_err(|_| "Invalid transaction signature")?;
+
+    let with_meta = VersionedTransactionWithStatusMeta {
+        transaction: convert_versioned_transaction(transaction)?,
+        meta: convert_meta(meta)?,
+    };
+
+    Ok(TransactionNotificationResult {
+        transaction: with_meta.encode(UiTransactionEncoding::JsonParsed, Some(0), true)?,
+        signature: signature.to_string(),
+        slot: update.slot,
+        frame: None,
+    })
+}
+
+fn convert_pubkey(bytes: &[u8]) -> Result<Pubkey, SourceError> {
+    Ok(Pubkey::try_from(bytes).map_err(|_| "Invalid public key")?)
+}
+
+fn convert_pubkeys(keys: &[Vec<u8>]) -> Result<Vec<Pubkey>, SourceError> {
+    keys.iter().map(|key| convert_pubkey(key)).collect()
+}
+
+fn convert_versioned_transaction(transaction: proto::Transaction) -> Result<VersionedTransaction, SourceError> {
+    let signatures = transaction.signatures.iter()
+        .map(|signature| Signature::try_from(signature.as_slice()).map_err(|_| "Invalid signature".into()))
+        .collect::<Result<Vec<Signature>, SourceError>>()?;
+
+    let message = transaction.message.ok_or("Transaction is missing its message")?;
+    let header = message.header.ok_or("Message is missing its header")?;
+    let header = MessageHeader {
+        num_required_signatures: header.num_required_signatures as u8,
+        num_readonly_signed_accounts: header.num_readonly_signed_accounts as u8,
+        num_readonly_unsigned_accounts: header.num_readonly_unsigned_accounts as u8,
+    };
+
+    let account_keys = convert_pubkeys(&message.account_keys)?;
+    let recent_blockhash = Hash::new(&message.recent_blockhash);
+    let instructions = message.instructions.into_iter()
+        .map(|instruction| CompiledInstruction {
+            program_id_index: instruction.program_id_index as u8,
+            accounts: instruction.accounts,
+            data: instruction.data,
+        })
+        .collect();
+
+    let message = if message.versioned {
+        let address_table_lookups = message.address_table_lookups.into_iter()
+            .map(|lookup| Ok(MessageAddressTableLookup {
+                account_key: convert_pubkey(&lookup.account_key)?,
+                writable_indexes: lookup.writable_indexes,
+                readonly_indexes: lookup.readonly_indexes,
+            }))
+            .collect::<Result<Vec<MessageAddressTableLookup>, SourceError>>()?;
+
+        VersionedMessage::V0(v0::Message {
+            header,
+            account_keys,
+            recent_blockhash,
+            instructions,
+            address_table_lookups,
+        })
+    } else {
+        VersionedMessage::Legacy(LegacyMessage {
+            header,
+            account_keys,
+            recent_blockhash,
+            instructions,
+        })
+    };
+
+    Ok(VersionedTransaction { signatures, message })
+}
+
+fn convert_token_balances(balances: Vec<proto::TokenBalance>) -> Vec<TransactionTokenBalance> {
+    balances.into_iter()
+        .map(|balance| {
+            let ui_token_amount = balance.ui_token_amount.unwrap_or_default();
+            TransactionTokenBalance {
+                account_index: balance.account_index as u8,
+                mint: balance.mint,
+                ui_token_amount: UiTokenAmount {
+                    ui_amount: Some(ui_token_amount.ui_amount).filter(|amount| *amount != 0.0),
+                    decimals: ui_token_amount.decimals as u8,
+                    amount: ui_token_amount.amount,
+                    ui_amount_string: ui_token_amount.ui_amount_string,
+                },
+                owner: balance.owner,
+                program_id: balance.program_id,
+            }
+        })
+        .collect()
+}
+
+fn convert_meta(meta: proto::TransactionStatusMeta) -> Result<TransactionStatusMeta, SourceError> {
+    let status = match meta.err {
+        Some(err) => Err(bincode::deserialize::<TransactionError>(&err.err)?),
+        None => Ok(()),
+    };
+
+    let inner_instructions = if meta.inner_instructions_none {
+        None
+    } else {
+        Some(meta.inner_instructions.into_iter()
+            .map(|inner| InnerInstructions {
+                index: inner.index as u8,
+                instructions: inner.instructions.into_iter()
+                    .map(|instruction| InnerInstruction {
+                        instruction: CompiledInstruction {
+                            program_id_index: instruction.program_id_index as u8,
+                            accounts: instruction.accounts,
+                            data: instruction.data,
+                        },
+                        stack_height: instruction.stack_height,
+                    })
+                    .collect(),
+            })
+            .collect())
+    };
+
+    let return_data = match meta.return_data {
+        Some(return_data) if !meta.return_data_none => Some(TransactionReturnData {
+            program_id: convert_pubkey(&return_data.program_id)?,
+            data: return_data.data,
+        }),
+        _ => None,
+    };
+
+    Ok(TransactionStatusMeta {
+        status,
+        fee: meta.fee,
+        pre_balances: meta.pre_balances,
+        post_balances: meta.post_balances,
+        inner_instructions,
+        log_messages: if meta.log_messages_none { None } else { Some(meta.log_messages) },
+        pre_token_balances: Some(convert_token_balances(meta.pre_token_balances)),
+        post_token_balances: Some(convert_token_balances(meta.post_token_balances)),
+        rewards: None,
+        loaded_addresses: LoadedAddresses {
+            writable: convert_pubkeys(&meta.loaded_writable_addresses)?,
+            readonly: convert_pubkeys(&meta.loaded_readonly_addresses)?,
+        },
+        return_data,
+        compute_units_consumed: meta.compute_units_consumed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::yellowstone::{
+        geyser_server::{Geyser, GeyserServer},
+        PingRequest,
+        PongResponse,
+        SubscribeUpdate,
+        SubscribeUpdatePing,
+        SubscribeUpdateTransactionInfo
+    };
+    use deadpool_redis::{Config, Runtime};
+    use futures::stream::{self, BoxStream};
+    use solana_sdk::{
+        instruction::InstructionError,
+        signature::Keypair,
+        signer::Signer,
+        system_transaction,
+        transaction::Transaction
+    };
+    use solana_transaction_status::{option_serializer::OptionSerializer, EncodedTransaction, UiMessage};
+    use std::{sync::{Arc, Mutex}, time::Duration};
+    use tokio::{net::TcpListener, sync::watch};
+    use tonic::{transport::Server, Response, Streaming};
+
+    use crate::sources::filter::FilterMode;
+
+    /// Answers every subscription with the same canned updates, and remembers the requests.
+    struct StubGeyser {
+        updates: Vec<SubscribeUpdate>,
+        requests: Arc<Mutex<Vec<SubscribeRequest>>>,
+    }
+
+    #[tonic::async_trait]
+    impl Geyser for StubGeyser {
+        type SubscribeStream = BoxStream<'static, Result<SubscribeUpdate, Status>>;
+
+        async fn subscribe(
+            &self,
+            request: Request<Streaming<SubscribeRequest>>
+        ) -> Result<Response<Self::SubscribeStream>, Status> {
+            let mut requests = request.into_inner();
+            if let Some(Ok(subscribe_request)) = requests.next().await {
+                self.requests.lock().unwrap().push(subscribe_request);
+            }
+
+            // The stream stays open after the canned updates, like a live subscription.
+            let updates = stream::iter(self.updates.clone().into_iter().map(Ok)).chain(stream::pending());
+            Ok(Response::new(Box::pin(updates)))
+        }
+
+        async fn ping(&self, request: Request<PingRequest>) -> Result<Response<PongResponse>, Status> {
+            Ok(Response::new(PongResponse { count: request.into_inner().count }))
+        }
+    }
+
+    async fn serve(stub: StubGeyser) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let incoming = stream::unfold(listener, |listener| async move {
+            let connection = listener.accept().await.map(|(stream, _)| stream);
+            Some((connection, listener))
+        });
+
+        tokio::spawn(Server::builder().add_service(GeyserServer::new(stub)).serve_with_incoming(incoming));
+        format!("http://{}", addr)
+    }
+
+    fn transaction_update(transaction: &Transaction, slot: u64, err: Option<TransactionError>) -> SubscribeUpdate {
+        let message = &transaction.message;
+        let proto_transaction = proto::Transaction {
+            signatures: transaction.signatures.iter().map(|signature| signature.as_ref().to_vec()).collect(),
+            message: Some(proto::Message {
+                header: Some(proto::MessageHeader {
+                    num_required_signatures: message.header.num_required_signatures as u32,
+                    num_readonly_signed_accounts: message.header.num_readonly_signed_accounts as u32,
+                    num_readonly_unsigned_accounts: message.header.num_readonly_unsigned_accounts as u32,
+                }),
+                account_keys: message.account_keys.iter().map(|key| key.to_bytes().to_vec()).collect(),
+                recent_blockhash: message.recent_blockhash.to_bytes().to_vec(),
+                instructions: message.instructions.iter()
+                    .map(|instruction| proto::CompiledInstruction {
+                        program_id_index: instruction.program_id_index as u32,
+                        accounts: instruction.accounts.clone(),
+                        data: instruction.data.clone(),
+                    })
+                    .collect(),
+                versioned: false,
+                address_table_lookups: vec![],
+            }),
+        };
+
+        let meta = proto::TransactionStatusMeta {
+            err: err.map(|err| proto::TransactionError { err: bincode::serialize(&err).unwrap() }),
+            fee: 5000,
+            pre_balances: vec![1_000_000_000, 0, 1],
+            post_balances: vec![999_994_000, 1000, 1],
+            log_messages: vec!["Program 11111111111111111111111111111111 invoke [1]".to_string()],
+            compute_units_consumed: Some(150),
+            ..Default::default()
+        };
+
+        SubscribeUpdate {
+            filters: vec![FILTER_NAME.to_string()],
+            update_oneof: Some(UpdateOneof::Transaction(SubscribeUpdateTransaction {
+                transaction: Some(SubscribeUpdateTransactionInfo {
+                    signature: transaction.signatures[0].as_ref().to_vec(),
+                    is_vote: false,
+                    transaction: Some(proto_transaction),
+                    meta: Some(meta),
+                    index: 0,
+                }),
+                slot,
+            })),
+        }
+    }
+
+    #[tokio::test]
+    async fn streams_converted_transactions() {
+        let payer = Keypair::new();
+        let recipient = Pubkey::new_unique();
+        let transfer = system_transaction::transfer(&payer, &recipient, 1000, Hash::new_unique());
+        let failed_transfer = system_transaction::transfer(&payer, &recipient, 2000, Hash::new_unique());
+        let failure = TransactionError::InstructionError(0, InstructionError::Custom(1));
+
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let endpoint = serve(StubGeyser {
+            updates: vec![
+                transaction_update(&transfer, 100, None),
+                SubscribeUpdate { filters: vec![], update_oneof: Some(UpdateOneof::Ping(SubscribeUpdatePing {})) },
+                transaction_update(&failed_transfer, 101, Some(failure.clone())),
+            ],
+            requests: requests.clone(),
+        }).await;
+
+        // Nothing is ever read from Redis in firehose mode.
+        let redis_pool = Config::from_url("redis://127.0.0.1:1").create_pool(Some(Runtime::Tokio1)).unwrap();
+        let (_, tracked_sets) = watch::channel(0);
+        let filter = SubscriptionFilter::new(FilterMode::Firehose, true, Arc::new(redis_pool), tracked_sets);
+        let source = GeyserSource::new(&endpoint, Some("token".to_string()), filter);
+
+        let stream = source.subscribe().await.unwrap();
+        let notifications: Vec<TransactionNotificationResult> = tokio::time::timeout(
+            Duration::from_secs(5),
+            stream.take(2).map(Result::unwrap).collect()
+        ).await.unwrap();
+
+        let requests = requests.lock().unwrap();
+        let transactions = &requests[0].transactions[FILTER_NAME];
+        assert_eq!(transactions.vote, Some(false));
+        assert_eq!(transactions.failed, None);
+        assert!(transactions.account_include.is_empty());
+
+        assert_eq!(notifications[0].signature, transfer.signatures[0].to_string());
+        assert_eq!(notifications[0].slot, 100);
+        assert_eq!(notifications[1].signature, failed_transfer.signatures[0].to_string());
+        assert_eq!(notifications[1].slot, 101);
+
+        let meta = notifications[0].transaction.meta.as_ref().unwrap();
+        assert_eq!(meta.err, None);
+        assert_eq!(meta.fee, 5000);
+        assert_eq!(meta.pre_balances, vec![1_000_000_000, 0, 1]);
+        assert_eq!(meta.post_balances, vec![999_994_000, 1000, 1]);
+        assert_eq!(meta.compute_units_consumed, OptionSerializer::Some(150));
+        assert_eq!(notifications[1].transaction.meta.as_ref().unwrap().err, Some(failure));
+
+        let ui_transaction = match &notifications[0].transaction.transaction {
+            EncodedTransaction::Json(ui_transaction) => ui_transaction,
+            other => panic!("Expected a jsonParsed transaction, got {:?}", other),
+        };
+        let message = match &ui_transaction.message {
+            UiMessage::Parsed(message) => message,
+            other => panic!("Expected a parsed message, got {:?}", other),
+        };
+        let account_keys: Vec<&str> = message.account_keys.iter().map(|account| account.pubkey.as_str()).collect();
+        assert_eq!(account_keys, vec![
+            payer.pubkey().to_string().as_str(),
+            recipient.to_string().as_str(),
+            "11111111111111111111111111111111",
+        ]);
+        assert!(message.account_keys[0].signer && message.account_keys[0].writable);
+        assert_eq!(message.recent_blockhash, transfer.message.recent_blockhash.to_string());
+        assert_eq!(message.instructions.len(), 1);
+    }
+}
@@ -0,0 +1,355 @@
+/// # Helius Transaction Source
+/// Streams transactions from the Helius Atlas `transactionSubscribe` enhanced websocket.
+///
+/// https://docs.helius.dev/webhooks-and-websockets/enhanced-websockets
+
+use tokio_tungstenite::{
+    connect_async,
+    MaybeTlsStream,
+    WebSocketStream,
+    tungstenite::{Error as WsError, Message as WsMessage}
+};
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use futures::{future::BoxFuture, stream::{SplitSink, SplitStream}};
+use futures_util::{SinkExt, StreamExt};
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+use tokio::{net::TcpStream, sync::Mutex};
+use tracing::{debug, info, warn};
+use std::sync::Arc;
+use solana_transaction_status::UiTransactionEncoding;
+
+use super::{filter::{FilterChanges, SubscriptionFilter}, NotificationStream, SourceError, TransactionNotificationResult, TransactionSource};
+
+type WsSink = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, WsMessage>;
+type WsRead = SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransactionNotificationParams {
+    pub subscription: u64,
+    pub result: TransactionNotificationResult
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransactionNotification {
+    pub jsonrpc: String,
+    pub method: String,
+    pub params: TransactionNotificationParams
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SubscriptionSuccessfulNotification {
+    id: String,
+    #[allow(dead_code)]
+    jsonrpc: String,
+    result: u64
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubscriptionErrorParams {
+    subscription: u64,
+    error: String
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct SubscriptionErrorNotification {
+    jsonrpc: String,
+    method: String,
+    params: SubscriptionErrorParams
+}
+
+#[derive(Debug, Deserialize)]
+struct UnsubscribeResponse {
+    id: String,
+    result: bool,
+}
+
+/// Tracks the subscriptions of a single connection. When the filter changes a new subscription
+/// is requested while the old one stays active, and the old one is only cancelled once the new
+/// one has been confirmed, so no transaction is missed in between. Notifications delivered by
+/// both during the overlap are de-duplicated by the processor.
+#[derive(Default)]
+struct SubscriptionState {
+    next_request_id: u64,
+    active: Option<u64>,
+    active_filter: Vec<String>,
+    pending: Option<(String, Vec<String>)>,
+    stale: bool,
+}
+
+#[derive(Clone)]
+pub struct HeliusSource {
+    name: String,
+    api_key: String,
+    url: String,
+    encoding: UiTransactionEncoding,
+    filter: SubscriptionFilter,
+    ws_write: Arc<Mutex<Option<WsSink>>>,
+}
+
+impl HeliusSource {
+    pub fn new(api_key: &str, url: &str, encoding: UiTransactionEncoding, filter: SubscriptionFilter) -> Self {
+        Self {
+            name: format!("helius({})", url),
+            api_key: api_key.to_string(),
+            url: url.to_string(),
+            encoding,
+            filter,
+            ws_write: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub async fn start_connection(&self) -> Result<WsRead, WsError> {
+        let ws_url = format!("wss://{}/?api-key={}", self.url, self.api_key);
+        info!("Connecting to: wss://{}/?api-key=*", self.url);
+
+        let (ws, _response) = connect_async(ws_url).await?;
+        info!("WebSocket connection established!");
+
+        let (write, read) = ws.split();
+
+        *self.ws_write.lock().await = Some(write);
+
+        Ok(read)
+    }
+
+    async fn send(&self, request: Value) -> Result<(), SourceError> {
+        let request_string = request.to_string();
+        let mut ws_guard = self.ws_write.lock().await;
+
+        if let Some(ws) = ws_guard.as_mut() {
+            ws.send(WsMessage::Text(request_string)).await?;
+            Ok(())
+        } else {
+            Err("WebSocket is not connected!".into())
+        }
+    }
+
+    /// Requests a new subscription built from the current tracked sets. The subscription only
+    /// becomes active once the server confirms it.
+    async fn subscribe_to_transactions(&self, state: &mut SubscriptionState) -> Result<(), SourceError> {
+        let account_include = self.filter.account_include().await?;
+        info!("Subscribing with {} included account(s).", account_include.len());
+
+        state.next_request_id += 1;
+        let request_id = state.next_request_id.to_string();
+
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": request_id,
+            "method": "transactionSubscribe",
+            "params": [
+                {
+                    "vote": false,
+                    "failed": self.filter.include_failed(),
+                    "accountInclude": account_include,
+                    "accountRequired": [],
+                    "accountExclude": [],
+                },
+                {
+                    "commitment": "processed",
+                    "encoding": self.encoding,
+                    "transaction_details": "full",
+                    "showRewards": true,
+                    "maxSupportedTransactionVersion": 0,
+                }
+            ]
+        });
+
+        self.send(request).await?;
+        state.pending = Some((request_id, account_include));
+        info!("Subscription to transactions successfully sent.");
+
+        Ok(())
+    }
+
+    /// Resubscribes if the tracked sets changed in a way that affects the filter.
+    async fn update_subscription(&self, state: &mut SubscriptionState) -> Result<(), SourceError> {
+        if state.pending.is_some() {
+            // Re-evaluated once the in-flight subscription is confirmed.
+            state.stale = true;
+            return Ok(());
+        }
+
+        let account_include = self.filter.account_include().await?;
+        if account_include == state.active_filter {
+            return Ok(());
+        }
+
+        info!(
+            "Tracked accounts changed ({} -> {}), resubscribing...",
+            state.active_filter.len(),
+            account_include.len()
+        );
+        self.subscribe_to_transactions(state).await
+    }
+
+    async fn unsubscribe(&self, state: &mut SubscriptionState, subscription: u64) -> Result<(), SourceError> {
+        state.next_request_id += 1;
+
+        self.send(json!({
+            "jsonrpc": "2.0",
+            "id": state.next_request_id.to_string(),
+            "method": "transactionUnsubscribe",
+            "params": [subscription]
+        })).await
+    }
+
+    /// Reads the websocket until it errors or closes, forwarding every transaction notification
+    /// into `sender`. Stops early if the receiving side of the channel has been dropped.
+    async fn process_messages(
+        &self,
+        mut read: WsRead,
+        mut state: SubscriptionState,
+        mut changes: FilterChanges,
+        sender: UnboundedSender<Result<TransactionNotificationResult, SourceError>>
+    ) {
+        let write_arc = self.ws_write.clone();
+        let heartbeat = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
+
+                let mut write_guard = write_arc.lock().await;
+                if let Some(ref mut sink) = *write_guard {
+                    if let Err(err) = sink.send(WsMessage::Ping(vec![])).await {
+                        warn!("Failed to send ping: {:?}", err);
+                        break;
+                    }
+                } else {
+                    warn!("Write sink is None, stopping heartbeat.");
+                    break;
+                }
+            }
+        });
+
+        loop {
+            let result = tokio::select! {
+                message = read.next() => match message {
+                    Some(Ok(message)) => self.handle_message(message, &mut state, &sender).await,
+                    Some(Err(err)) => Err(err.into()),
+                    None => break,
+                },
+                _ = changes.changed() => self.update_subscription(&mut state).await,
+            };
+
+            if let Err(err) = result {
+                // If we encounter an error (like a subscription error that couldn't be fixed
+                // or a read error), this will trigger the logic in `start_processor` to
+                // reconnect and/or resubscribe.
+                warn!("Error processing messages: {}", err);
+                let _ = sender.send(Err(err));
+                break;
+            }
+
+            if sender.is_closed() {
+                break;
+            }
+        }
+
+        heartbeat.abort();
+    }
+
+    async fn handle_message(
+        &self,
+        message: WsMessage,
+        state: &mut SubscriptionState,
+        sender: &UnboundedSender<Result<TransactionNotificationResult, SourceError>>
+    ) -> Result<(), SourceError> {
+        match message {
+            WsMessage::Text(text) => {
+                let json: Value = serde_json::from_str(&text)
+                    .map_err(|e| format!("JSON parse error: {}", e))?;
+
+                if let Ok(notification) = serde_json::from_value::<TransactionNotification>(json.clone()) {
+                    let mut result = notification.params.result;
+                    result.frame = Some(Arc::from(text));
+                    let _ = sender.send(Ok(result));
+                    return Ok(());
+                }
+
+                if let Ok(notification) = serde_json::from_value::<SubscriptionSuccessfulNotification>(json.clone()) {
+                    info!("Subscription successful: {:?}", notification);
+
+                    let confirmed = state.pending.as_ref()
+                        .map(|(request_id, _)| *request_id == notification.id)
+                        .unwrap_or(false);
+
+                    if confirmed {
+                        let (_, filter) = state.pending.take().unwrap();
+                        state.active_filter = filter;
+
+                        if let Some(previous) = state.active.replace(notification.result) {
+                            info!("Cancelling previous subscription {}.", previous);
+                            self.unsubscribe(state, previous).await?;
+                        }
+
+                        if state.stale {
+                            state.stale = false;
+                            self.update_subscription(state).await?;
+                        }
+                    }
+                    return Ok(());
+                }
+
+                if let Ok(response) = serde_json::from_value::<UnsubscribeResponse>(json.clone()) {
+                    info!("Unsubscribe request {} completed: {}", response.id, response.result);
+                    return Ok(());
+                }
+
+                if let Ok(notification) = serde_json::from_value::<SubscriptionErrorNotification>(json.clone()) {
+                    warn!("Subscription error: {:?}", notification);
+                    warn!("Sending re-subscription request...");
+
+                    state.pending = None;
+                    self.subscribe_to_transactions(state).await
+                        .map_err(|e| format!("Failed to re-subscribe after error: {}", e))?;
+
+                    info!("Re-subscription successful after error.");
+                    return Ok(());
+                }
+
+                warn!("Unknown JSON message: {}", text);
+                Ok(())
+            }
+            WsMessage::Pong(_data) => {
+                debug!("WebSocket server responded with Pong.");
+                Ok(())
+            }
+            WsMessage::Close(frame) => {
+                warn!("WebSocket connection closed: {:?}", frame);
+                Ok(())
+            }
+            other => {
+                warn!("Unknown message: {:?}", other);
+                Ok(())
+            }
+        }
+    }
+}
+
+impl TransactionSource for HeliusSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn subscribe(&self) -> BoxFuture<'_, Result<NotificationStream, SourceError>> {
+        Box::pin(async move {
+            let read = self.start_connection().await?;
+
+            let changes = self.filter.changes();
+            let mut state = SubscriptionState::default();
+            self.subscribe_to_transactions(&mut state).await?;
+
+            let (sender, receiver) = mpsc::unbounded_channel();
+            let this = self.clone();
+            tokio::spawn(async move {
+                this.process_messages(read, state, changes, sender).await;
+            });
+
+            Ok(Box::pin(UnboundedReceiverStream::new(receiver)) as NotificationStream)
+        })
+    }
+}
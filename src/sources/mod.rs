@@ -0,0 +1,195 @@
+/// # Transaction Sources
+/// A transaction source is anything able to produce a stream of transactions in the
+/// normalized `TransactionNotificationResult` shape. The `TransactionProcessor` only ever
+/// consumes this trait, which allows the decoding logic to be fed by Helius, a standard
+/// Solana RPC node, a geyser feed or a recording without any changes to the handlers.
+
+use solana_transaction_status::{EncodedTransactionWithStatusMeta, UiTransactionEncoding};
+use futures::{future::BoxFuture, stream::BoxStream};
+use serde::{Serialize, Deserialize};
+use deadpool_redis::Pool;
+use tokio::sync::watch;
+use std::{env, sync::Arc, time::Duration};
+
+use crate::processors::commitments::commitment::CommitmentStatus;
+
+use backfill::Backfiller;
+use filter::{FilterMode, SubscriptionFilter};
+use geyser::GeyserSource;
+use helius::HeliusSource;
+use multi::MultiSource;
+use recording::{Recorder, RecordingSource};
+use replay::{ReplaySource, ReplaySpeed};
+use rpc::{RpcSource, RpcSubscriptionMode};
+
+pub mod backfill;
+pub mod filter;
+pub mod geyser;
+pub mod helius;
+pub mod multi;
+pub mod recording;
+pub mod replay;
+pub mod rpc;
+
+pub type SourceError = Box<dyn std::error::Error + Send + Sync>;
+
+pub type NotificationStream = BoxStream<'static, Result<TransactionNotificationResult, SourceError>>;
+
+/// https://github.com/helius-labs/helius-rust-sdk/blob/dev/src/types/enhanced_websocket.rs#L96
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionNotificationResult {
+    pub transaction: EncodedTransactionWithStatusMeta,
+    pub signature: String,
+    pub slot: u64,
+    /// The frame exactly as it was received, for providers sending a JSON frame per transaction
+    /// (Helius). Only used to record notifications.
+    #[serde(skip)]
+    pub frame: Option<Arc<str>>,
+}
+
+pub trait TransactionSource: Send + Sync {
+    /// Human readable name of the source, used for logging.
+    fn name(&self) -> &str;
+
+    /// Connects and subscribes to the underlying provider. The returned stream yields every
+    /// transaction received for the lifetime of the connection. An `Err` item (or the stream
+    /// ending) signals that the connection was lost and `subscribe` should be called again.
+    fn subscribe(&self) -> BoxFuture<'_, Result<NotificationStream, SourceError>>;
+
+    /// Whether the stream ending means the connection was lost. Finite sources, such as a
+    /// replay, return `false` and are not subscribed again once their stream has ended.
+    fn reconnects(&self) -> bool {
+        true
+    }
+
+    /// The commitment transactions are streamed at, which is the status an observed signature
+    /// starts out with.
+    fn commitment(&self) -> CommitmentStatus {
+        CommitmentStatus::Processed
+    }
+}
+
+/// Builds the transaction source(s) selected through the `TRANSACTION_SOURCE` env var. Several
+/// comma separated kinds (e.g. `helius,geyser`) run concurrently behind a `MultiSource`.
+///
+/// - `helius` (default): requires `HELIUS_RPC_API_KEY`, optionally `HELIUS_WS_HOST` which also
+///   accepts a comma separated list of hosts (e.g. two regions), and `HELIUS_ENCODING` (`jsonParsed`
+///   by default, `base64` is considerably smaller and is decoded locally).
+/// - `rpc`: uses `SOLANA_WS_URL`, `SOLANA_RPC_URL` and `SOLANA_SUBSCRIPTION` (`logs` or `block`),
+///   defaulting to a local `solana-test-validator`.
+/// - `geyser`: uses `GEYSER_ENDPOINT` and the optional `GEYSER_X_TOKEN`.
+/// - `replay`: replays the recordings at `REPLAY_PATH` at `REPLAY_SPEED` (`original`, `max` or a
+///   speed-up factor).
+///
+/// Providers supporting server-side filters (`helius` and `geyser`) stream every non-vote
+/// transaction by default. Setting `SUBSCRIPTION_FILTER` to `narrow` restricts them to the
+/// transactions touching tracked programs and addresses, following changes to the tracked sets
+/// without requiring a restart.
+///
+/// Transactions that failed on chain are only streamed when `INCLUDE_FAILED_TRANSACTIONS` is
+/// `true`.
+///
+/// When `RECORD_DIR` is set, every notification is additionally recorded to that directory, in
+/// a new file every `RECORD_ROTATE_SECS` (default one hour).
+///
+/// `tracked_sets` is the shared watcher of the tracked sets, see `filter::watch_tracked_sets`.
+/// `backfiller` recovers the gap of each source that reconnects behind a `MultiSource`.
+pub fn source_from_env(
+    redis_pool: Arc<Pool>,
+    tracked_sets: watch::Receiver<u64>,
+    backfiller: Option<Arc<Backfiller>>
+) -> Result<Arc<dyn TransactionSource>, SourceError> {
+    let kinds = env::var("TRANSACTION_SOURCE").unwrap_or_else(|_| "helius".to_string());
+
+    let filter_name = env::var("SUBSCRIPTION_FILTER").unwrap_or_else(|_| "firehose".to_string());
+    let filter_mode = FilterMode::from_name(&filter_name)
+        .ok_or_else(|| format!("Unknown SUBSCRIPTION_FILTER: {}", filter_name))?;
+    let filter = SubscriptionFilter::new(filter_mode, include_failed_from_env(), redis_pool, tracked_sets);
+
+    let mut sources = Vec::new();
+    for kind in kinds.split(',').map(str::trim).filter(|kind| !kind.is_empty()) {
+        sources.extend(sources_for_kind(kind, &filter)?);
+    }
+
+    let source: Arc<dyn TransactionSource> = match sources.len() {
+        0 => return Err("TRANSACTION_SOURCE does not name any source".into()),
+        1 => sources.remove(0),
+        _ => Arc::new(MultiSource::new(sources, backfiller)),
+    };
+
+    match env::var("RECORD_DIR") {
+        Ok(directory) => {
+            let rotate_secs = env::var("RECORD_ROTATE_SECS")
+                .ok()
+                .and_then(|secs| secs.parse::<u64>().ok())
+                .unwrap_or(3600);
+            let recorder = Recorder::new(&directory, Duration::from_secs(rotate_secs))?;
+            Ok(Arc::new(RecordingSource::new(source, recorder)))
+        }
+        Err(_) => Ok(source),
+    }
+}
+
+fn sources_for_kind(kind: &str, filter: &SubscriptionFilter) -> Result<Vec<Arc<dyn TransactionSource>>, SourceError> {
+    match kind.to_lowercase().as_str() {
+        "helius" => {
+            let api_key = env::var("HELIUS_RPC_API_KEY").map_err(|_| "HELIUS_RPC_API_KEY must be set")?;
+            let hosts = env::var("HELIUS_WS_HOST").unwrap_or_else(|_| "atlas-mainnet.helius-rpc.com".to_string());
+            let encoding_name = env::var("HELIUS_ENCODING").unwrap_or_else(|_| "jsonParsed".to_string());
+            let encoding = match encoding_name.as_str() {
+                "jsonParsed" => UiTransactionEncoding::JsonParsed,
+                "json" => UiTransactionEncoding::Json,
+                "base64" => UiTransactionEncoding::Base64,
+                "base58" => UiTransactionEncoding::Base58,
+                other => return Err(format!("Unknown HELIUS_ENCODING: {}", other).into()),
+            };
+            Ok(hosts.split(',')
+                .map(str::trim)
+                .filter(|host| !host.is_empty())
+                .map(|host| Arc::new(HeliusSource::new(&api_key, host, encoding, filter.clone())) as Arc<dyn TransactionSource>)
+                .collect())
+        }
+        "rpc" => {
+            let ws_url = env::var("SOLANA_WS_URL").unwrap_or_else(|_| "ws://127.0.0.1:8900".to_string());
+            let http_url = env::var("SOLANA_RPC_URL").unwrap_or_else(|_| "http://127.0.0.1:8899".to_string());
+            let mode_name = env::var("SOLANA_SUBSCRIPTION").unwrap_or_else(|_| "logs".to_string());
+            let mode = RpcSubscriptionMode::from_name(&mode_name)
+                .ok_or_else(|| format!("Unknown SOLANA_SUBSCRIPTION mode: {}", mode_name))?;
+            Ok(vec![Arc::new(RpcSource::new(&ws_url, &http_url, mode, filter.include_failed()))])
+        }
+        "geyser" => {
+            let endpoint = env::var("GEYSER_ENDPOINT").unwrap_or_else(|_| "http://127.0.0.1:10000".to_string());
+            let x_token = env::var("GEYSER_X_TOKEN").ok();
+            Ok(vec![Arc::new(GeyserSource::new(&endpoint, x_token, filter.clone()))])
+        }
+        "replay" => {
+            let path = env::var("REPLAY_PATH").map_err(|_| "REPLAY_PATH must be set")?;
+            let speed_name = env::var("REPLAY_SPEED").unwrap_or_else(|_| "original".to_string());
+            let speed = ReplaySpeed::from_name(&speed_name)
+                .ok_or_else(|| format!("Unknown REPLAY_SPEED: {}", speed_name))?;
+            Ok(vec![Arc::new(ReplaySource::new(&path, speed))])
+        }
+        other => Err(format!("Unknown TRANSACTION_SOURCE: {}", other).into()),
+    }
+}
+
+/// Builds the backfiller used to recover transactions missed while a source was reconnecting.
+/// Backfilling is enabled by setting `BACKFILL_RPC_URL`, `BACKFILL_MAX_PAGES` bounds how far back
+/// each tracked program is paged (default 5 pages of 1000 signatures).
+pub fn backfiller_from_env() -> Option<Backfiller> {
+    let rpc_url = env::var("BACKFILL_RPC_URL").ok()?;
+    let max_pages = env::var("BACKFILL_MAX_PAGES")
+        .ok()
+        .and_then(|pages| pages.parse::<usize>().ok())
+        .unwrap_or(5);
+
+    Some(Backfiller::new(&rpc_url, max_pages, include_failed_from_env()))
+}
+
+/// Failed transactions are ignored by default, they are only useful for analysis such as the
+/// competition between snipers for the same pump.fun launch.
+pub fn include_failed_from_env() -> bool {
+    env::var("INCLUDE_FAILED_TRANSACTIONS")
+        .map(|value| value.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
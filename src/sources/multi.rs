@@ -0,0 +1,147 @@
+/// # Redundant Multi-Provider Source
+/// Runs several transaction sources concurrently and merges them into a single stream. Every
+/// transaction is forwarded once, by whichever source delivered it first, so the processor
+/// still decodes and publishes each signature exactly once. A dropped connection on one
+/// provider is covered by the others while it reconnects in the background.
+///
+/// Each source reconnects on its own, so the gap of a reconnecting source is backfilled here,
+/// from the last slot that source delivered, rather than by the `TransactionProcessor`.
+
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use futures::{future::BoxFuture, StreamExt};
+use std::sync::{Arc, Mutex};
+use tracing::{info, warn};
+
+use crate::{helpers::signature_cache::SignatureCache, processors::commitments::commitment::CommitmentStatus};
+
+use super::{backfill::Backfiller, NotificationStream, SourceError, TransactionNotificationResult, TransactionSource};
+
+/// Duplicates from slower providers usually arrive within a few hundred milliseconds, this
+/// leaves ample room even at peak throughput.
+const DEDUP_CAPACITY: usize = 50_000;
+
+pub struct MultiSource {
+    name: String,
+    sources: Vec<Arc<dyn TransactionSource>>,
+    backfiller: Option<Arc<Backfiller>>,
+}
+
+impl MultiSource {
+    pub fn new(sources: Vec<Arc<dyn TransactionSource>>, backfiller: Option<Arc<Backfiller>>) -> Self {
+        let names: Vec<&str> = sources.iter().map(|source| source.name()).collect();
+
+        Self {
+            name: format!("multi[{}]", names.join(", ")),
+            sources,
+            backfiller,
+        }
+    }
+}
+
+/// Keeps a single source subscribed for as long as the merged stream is being consumed,
+/// forwarding only the signatures that no other source has delivered yet.
+async fn run_source(
+    source: Arc<dyn TransactionSource>,
+    backfiller: Option<Arc<Backfiller>>,
+    seen: Arc<Mutex<SignatureCache>>,
+    sender: UnboundedSender<Result<TransactionNotificationResult, SourceError>>
+) {
+    let forward = |notification: TransactionNotificationResult| {
+        let first_seen = seen.lock().unwrap().insert(&notification.signature);
+        !first_seen || sender.send(Ok(notification)).is_ok()
+    };
+
+    // Most recent slot delivered by this source, duplicates included.
+    let mut last_slot: Option<u64> = None;
+
+    while !sender.is_closed() {
+        let mut stream = match source.subscribe().await {
+            Ok(stream) => stream,
+            Err(err) => {
+                warn!("Failed to subscribe to {}: {}. Retrying in 1 second(s)...", source.name(), err);
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+
+        // The live stream is already buffering at this point, so once the gap has been
+        // backfilled nothing that landed in between can be missed.
+        match (&backfiller, last_slot) {
+            (Some(backfiller), Some(last_slot)) => match backfiller.backfill(last_slot).await {
+                Ok(notifications) => {
+                    if !notifications.into_iter().all(forward) {
+                        return;
+                    }
+                    info!("Backfill of {} since slot {} completed, resuming live processing.", source.name(), last_slot);
+                }
+                Err(err) => warn!("Failed to backfill {} since slot {}: {}", source.name(), last_slot, err),
+            },
+            (None, Some(last_slot)) => {
+                warn!("Reconnected to {} without a backfill RPC, transactions since slot {} may be missing.", source.name(), last_slot);
+            }
+            _ => {}
+        }
+
+        while let Some(notification) = stream.next().await {
+            match notification {
+                Ok(notification) => {
+                    last_slot = Some(last_slot.map_or(notification.slot, |last| last.max(notification.slot)));
+                    if !forward(notification) {
+                        return;
+                    }
+                }
+                Err(err) => {
+                    warn!("Error while processing messages from {}: {}", source.name(), err);
+                    break;
+                }
+            }
+        }
+
+        if !source.reconnects() {
+            info!("{} stream finished.", source.name());
+            return;
+        }
+
+        warn!("{} stream closed. Will retry in 1 second(s)...", source.name());
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    }
+}
+
+impl TransactionSource for MultiSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The merged stream only ends once every source has ended for good.
+    fn reconnects(&self) -> bool {
+        self.sources.iter().any(|source| source.reconnects())
+    }
+
+    /// A transaction is forwarded by whichever source delivers it first, which may be the one
+    /// streaming at the lowest commitment.
+    fn commitment(&self) -> CommitmentStatus {
+        self.sources.iter()
+            .map(|source| source.commitment())
+            .min_by_key(|commitment| *commitment as i32)
+            .unwrap_or(CommitmentStatus::Processed)
+    }
+
+    fn subscribe(&self) -> BoxFuture<'_, Result<NotificationStream, SourceError>> {
+        Box::pin(async move {
+            if self.sources.is_empty() {
+                return Err("No transaction sources configured!".into());
+            }
+
+            let seen = Arc::new(Mutex::new(SignatureCache::new(DEDUP_CAPACITY)));
+            let (sender, receiver) = mpsc::unbounded_channel();
+
+            for source in &self.sources {
+                info!("Starting redundant source {}", source.name());
+                tokio::spawn(run_source(source.clone(), self.backfiller.clone(), seen.clone(), sender.clone()));
+            }
+
+            Ok(Box::pin(UnboundedReceiverStream::new(receiver)) as NotificationStream)
+        })
+    }
+}
@@ -0,0 +1,214 @@
+/// # Notification Recording
+/// Wraps any transaction source and writes every notification it yields, together with the
+/// time it was received, to rotating gzip compressed JSONL files. For Helius each line holds
+/// the `transactionNotification` frame exactly as it was received, other sources record the
+/// normalized notification. Either can be replayed with `ReplaySource` to deterministically
+/// reproduce production incidents.
+///
+/// Compression and file I/O happen on a dedicated thread, the stream only hands notifications
+/// over through a channel.
+
+use flate2::{write::GzEncoder, Compression};
+use futures::{future::BoxFuture, StreamExt};
+use serde::{Serialize, Deserialize};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use std::{fs::{self, File}, io::{BufWriter, Write}, path::PathBuf, sync::Arc, thread};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+use chrono::Utc;
+
+use crate::processors::commitments::commitment::CommitmentStatus;
+
+use super::{
+    helius::TransactionNotification,
+    NotificationStream,
+    SourceError,
+    TransactionNotificationResult,
+    TransactionSource
+};
+
+/// Number of lines written between two flushes of the compressed stream. Flushing every
+/// line would hurt the compression ratio considerably.
+const FLUSH_EVERY: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecordedNotification {
+    /// Unix timestamp in milliseconds at which the notification was received.
+    pub received_at: i64,
+    /// The raw `transactionNotification` frame. Older recordings named it `notification`.
+    #[serde(default, alias = "notification", skip_serializing_if = "Option::is_none")]
+    pub frame: Option<TransactionNotification>,
+    /// The normalized notification, for sources without a frame per transaction.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<TransactionNotificationResult>,
+}
+
+impl RecordedNotification {
+    pub fn into_result(self) -> Option<TransactionNotificationResult> {
+        self.frame.map(|frame| frame.params.result).or(self.result)
+    }
+}
+
+enum Record {
+    Frame(Arc<str>),
+    Result(Box<TransactionNotificationResult>),
+}
+
+struct RecordingFile {
+    writer: GzEncoder<BufWriter<File>>,
+    opened_at: Instant,
+    unflushed: usize,
+}
+
+/// Owns the recording files, runs on the recorder thread.
+struct RecordingWriter {
+    directory: PathBuf,
+    rotate_after: Duration,
+    file: Option<RecordingFile>,
+}
+
+impl RecordingWriter {
+    fn open_file(&self) -> Result<RecordingFile, SourceError> {
+        let file_name = format!("notifications-{}.jsonl.gz", Utc::now().format("%Y%m%dT%H%M%S%3f"));
+        let path = self.directory.join(file_name);
+        info!("Recording notifications to {}", path.display());
+
+        Ok(RecordingFile {
+            writer: GzEncoder::new(BufWriter::new(File::create(path)?), Compression::default()),
+            opened_at: Instant::now(),
+            unflushed: 0,
+        })
+    }
+
+    fn write(&mut self, received_at: i64, record: Record) -> Result<(), SourceError> {
+        let expired = self.file.as_ref()
+            .map(|file| file.opened_at.elapsed() >= self.rotate_after)
+            .unwrap_or(true);
+
+        if expired {
+            self.finish()?;
+            self.file = Some(self.open_file()?);
+        }
+
+        if let Some(file) = self.file.as_mut() {
+            match record {
+                // Written by hand so that the frame is kept byte for byte.
+                Record::Frame(frame) => write!(file.writer, "{{\"received_at\":{},\"frame\":{}}}", received_at, frame)?,
+                Record::Result(result) => serde_json::to_writer(&mut file.writer, &RecordedNotification {
+                    received_at,
+                    frame: None,
+                    result: Some(*result),
+                })?,
+            }
+            file.writer.write_all(b"\n")?;
+
+            file.unflushed += 1;
+            if file.unflushed >= FLUSH_EVERY {
+                file.writer.flush()?;
+                file.unflushed = 0;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), SourceError> {
+        if let Some(file) = self.file.take() {
+            file.writer.finish()?.flush()?;
+        }
+        Ok(())
+    }
+
+    fn run(mut self, mut receiver: UnboundedReceiver<(i64, Record)>) {
+        while let Some((received_at, record)) = receiver.blocking_recv() {
+            if let Err(err) = self.write(received_at, record) {
+                warn!("Failed to record notification: {}", err);
+            }
+        }
+
+        if let Err(err) = self.finish() {
+            warn!("Failed to finish recording: {}", err);
+        }
+    }
+}
+
+/// Hands notifications over to the recorder thread, which finishes the current file once
+/// every handle has been dropped.
+#[derive(Clone)]
+pub struct Recorder {
+    sender: UnboundedSender<(i64, Record)>,
+}
+
+impl Recorder {
+    pub fn new(directory: &str, rotate_after: Duration) -> Result<Self, SourceError> {
+        fs::create_dir_all(directory)?;
+
+        let writer = RecordingWriter {
+            directory: PathBuf::from(directory),
+            rotate_after,
+            file: None,
+        };
+        let (sender, receiver) = mpsc::unbounded_channel();
+        thread::Builder::new()
+            .name("recorder".to_string())
+            .spawn(move || writer.run(receiver))?;
+
+        Ok(Self { sender })
+    }
+
+    pub fn record(&self, received_at: i64, result: &TransactionNotificationResult) {
+        let record = match &result.frame {
+            Some(frame) => Record::Frame(frame.clone()),
+            None => Record::Result(Box::new(result.clone())),
+        };
+
+        if self.sender.send((received_at, record)).is_err() {
+            warn!("Recorder stopped, notification {} was not recorded.", result.signature);
+        }
+    }
+}
+
+pub struct RecordingSource {
+    name: String,
+    inner: Arc<dyn TransactionSource>,
+    recorder: Recorder,
+}
+
+impl RecordingSource {
+    pub fn new(inner: Arc<dyn TransactionSource>, recorder: Recorder) -> Self {
+        Self {
+            name: format!("recording({})", inner.name()),
+            inner,
+            recorder,
+        }
+    }
+}
+
+impl TransactionSource for RecordingSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn reconnects(&self) -> bool {
+        self.inner.reconnects()
+    }
+
+    fn commitment(&self) -> CommitmentStatus {
+        self.inner.commitment()
+    }
+
+    fn subscribe(&self) -> BoxFuture<'_, Result<NotificationStream, SourceError>> {
+        Box::pin(async move {
+            let stream = self.inner.subscribe().await?;
+            let recorder = self.recorder.clone();
+
+            let stream = stream.inspect(move |notification| {
+                if let Ok(result) = notification {
+                    recorder.record(Utc::now().timestamp_millis(), result);
+                }
+            });
+
+            Ok(Box::pin(stream) as NotificationStream)
+        })
+    }
+}
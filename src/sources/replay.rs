@@ -0,0 +1,175 @@
+/// # Notification Replay
+/// Feeds files written by the `Recorder` back through the transaction processor, either at
+/// the speed they were originally received, accelerated by a constant factor, or as fast as
+/// the handlers are able to process them. Once every file has been replayed the stream ends.
+
+use flate2::read::MultiGzDecoder;
+use futures::future::BoxFuture;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use std::{fs::{self, File}, io::{BufRead, BufReader}, path::{Path, PathBuf}};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use super::{recording::RecordedNotification, NotificationStream, SourceError, TransactionSource};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplaySpeed {
+    Original,
+    Accelerated(f64),
+    Max,
+}
+
+impl ReplaySpeed {
+    /// Accepts `original`, `max`, or a numeric speed-up factor such as `10`.
+    pub fn from_name(speed: &str) -> Option<ReplaySpeed> {
+        match speed.to_lowercase().as_str() {
+            "original" => Some(ReplaySpeed::Original),
+            "max" => Some(ReplaySpeed::Max),
+            factor => factor.parse::<f64>().ok()
+                .filter(|factor| *factor > 0.0)
+                .map(ReplaySpeed::Accelerated),
+        }
+    }
+
+    fn factor(&self) -> Option<f64> {
+        match self {
+            ReplaySpeed::Original => Some(1.0),
+            ReplaySpeed::Accelerated(factor) => Some(*factor),
+            ReplaySpeed::Max => None,
+        }
+    }
+}
+
+pub struct ReplaySource {
+    name: String,
+    path: PathBuf,
+    speed: ReplaySpeed,
+}
+
+impl ReplaySource {
+    pub fn new(path: &str, speed: ReplaySpeed) -> Self {
+        Self {
+            name: format!("replay({})", path),
+            path: PathBuf::from(path),
+            speed,
+        }
+    }
+
+    /// Either the single file that was configured, or every recording in the directory sorted
+    /// by name (which, given the timestamped file names, is chronological).
+    fn files(&self) -> Result<Vec<PathBuf>, SourceError> {
+        if self.path.is_file() {
+            return Ok(vec![self.path.clone()]);
+        }
+
+        let mut files: Vec<PathBuf> = fs::read_dir(&self.path)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file())
+            .collect();
+        files.sort();
+
+        Ok(files)
+    }
+}
+
+fn read_file(path: &Path, sender: &mpsc::Sender<RecordedNotification>) -> Result<(), SourceError> {
+    info!("Replaying notifications from {}", path.display());
+
+    let file = File::open(path)?;
+    let reader: Box<dyn BufRead> = if path.extension().map(|ext| ext == "gz").unwrap_or(false) {
+        Box::new(BufReader::new(MultiGzDecoder::new(file)))
+    } else {
+        Box::new(BufReader::new(file))
+    };
+
+    for line in reader.lines() {
+        // A recording that was interrupted mid-write ends with a truncated gzip stream, the
+        // lines up to that point are still perfectly usable.
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                warn!("Stopped reading {}: {}", path.display(), err);
+                break;
+            }
+        };
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match serde_json::from_str::<RecordedNotification>(&line) {
+            Ok(record) => {
+                if sender.blocking_send(record).is_err() {
+                    return Err("Replay stream was dropped".into());
+                }
+            }
+            Err(err) => warn!("Skipping malformed line in {}: {}", path.display(), err),
+        }
+    }
+
+    Ok(())
+}
+
+impl TransactionSource for ReplaySource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn reconnects(&self) -> bool {
+        false
+    }
+
+    fn subscribe(&self) -> BoxFuture<'_, Result<NotificationStream, SourceError>> {
+        Box::pin(async move {
+            let files = self.files()?;
+            if files.is_empty() {
+                return Err(format!("No recordings found at {}", self.path.display()).into());
+            }
+
+            let (record_sender, mut record_receiver) = mpsc::channel::<RecordedNotification>(1024);
+            tokio::task::spawn_blocking(move || {
+                for file in files {
+                    if let Err(err) = read_file(&file, &record_sender) {
+                        warn!("Failed to replay {}: {}", file.display(), err);
+                        break;
+                    }
+                }
+            });
+
+            let (sender, receiver) = mpsc::unbounded_channel();
+            let speed = self.speed;
+            tokio::spawn(async move {
+                let started = Instant::now();
+                let mut first_received_at: Option<i64> = None;
+                let mut replayed: u64 = 0;
+
+                while let Some(record) = record_receiver.recv().await {
+                    if let Some(factor) = speed.factor() {
+                        let first = *first_received_at.get_or_insert(record.received_at);
+                        let offset_ms = record.received_at.saturating_sub(first).max(0) as f64 / factor;
+                        let due = started + Duration::from_secs_f64(offset_ms / 1000.0);
+                        tokio::time::sleep_until(due.into()).await;
+                    }
+
+                    let result = match record.into_result() {
+                        Some(result) => result,
+                        None => {
+                            warn!("Skipping recorded line without a notification.");
+                            continue;
+                        }
+                    };
+
+                    if sender.send(Ok(result)).is_err() {
+                        break;
+                    }
+                    replayed += 1;
+                }
+
+                info!("Replay finished after {} notification(s).", replayed);
+            });
+
+            Ok(Box::pin(UnboundedReceiverStream::new(receiver)) as NotificationStream)
+        })
+    }
+}
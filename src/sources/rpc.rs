@@ -0,0 +1,385 @@
+/// # Standard Solana RPC Transaction Source
+/// Streams transactions from any vanilla Solana RPC node, including a local
+/// `solana-test-validator`, without relying on Helius specific subscriptions.
+///
+/// Two subscription modes are supported:
+/// - `Block`: `blockSubscribe` with full transaction details. Requires the node to run with
+///   `--rpc-pubsub-enable-block-subscription`.
+/// - `Logs`: one `logsSubscribe` per tracked program in `ProgramId`, followed by a
+///   `getTransaction` request for every signature that is mentioned.
+///
+/// Neither `blockSubscribe` nor `getTransaction` support the `processed` commitment, so this
+/// source always operates at `confirmed`.
+
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta,
+    EncodedTransaction,
+    EncodedTransactionWithStatusMeta,
+    UiConfirmedBlock,
+    UiMessage
+};
+use tokio_tungstenite::{connect_async, tungstenite::Message as WsMessage};
+use tokio::sync::mpsc::{self, Receiver, Sender, UnboundedSender};
+use tokio_stream::wrappers::{ReceiverStream, UnboundedReceiverStream};
+use futures_util::{SinkExt, StreamExt};
+use futures::future::BoxFuture;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::{info, warn};
+use std::time::Duration;
+
+use crate::helpers::{json_rpc::JsonRpcClient, signature_cache::SignatureCache};
+use crate::processors::commitments::commitment::CommitmentStatus;
+use crate::programs::ProgramId;
+
+use super::{NotificationStream, SourceError, TransactionNotificationResult, TransactionSource};
+
+const VOTE_PROGRAM_ID: &str = "Vote111111111111111111111111111111111111111";
+
+/// Number of attempts made to fetch a transaction that was just notified. The transaction is
+/// not always retrievable through `getTransaction` the instant it is notified.
+const GET_TRANSACTION_ATTEMPTS: usize = 5;
+
+/// Maximum number of `getTransaction` fetches in flight, and of signatures queued behind them.
+/// Once both are reached, reading the websocket waits for a fetch to complete rather than
+/// queueing fetches without bound.
+const MAX_CONCURRENT_FETCHES: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RpcSubscriptionMode {
+    Block,
+    Logs,
+}
+
+impl RpcSubscriptionMode {
+    pub fn from_name(mode: &str) -> Option<RpcSubscriptionMode> {
+        match mode.to_lowercase().as_str() {
+            "block" => Some(RpcSubscriptionMode::Block),
+            "logs" => Some(RpcSubscriptionMode::Logs),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcNotificationContext {
+    slot: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcNotificationResult<T> {
+    context: RpcNotificationContext,
+    value: T,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcNotificationParams<T> {
+    result: RpcNotificationResult<T>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcNotification<T> {
+    method: String,
+    params: RpcNotificationParams<T>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BlockNotificationValue {
+    slot: u64,
+    block: Option<UiConfirmedBlock>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LogsNotificationValue {
+    signature: String,
+    err: Option<Value>,
+}
+
+#[derive(Clone)]
+pub struct RpcSource {
+    name: String,
+    ws_url: String,
+    rpc_client: JsonRpcClient,
+    mode: RpcSubscriptionMode,
+    include_failed: bool,
+}
+
+impl RpcSource {
+    pub fn new(ws_url: &str, http_url: &str, mode: RpcSubscriptionMode, include_failed: bool) -> Self {
+        Self {
+            name: format!("rpc({})", ws_url),
+            ws_url: ws_url.to_string(),
+            rpc_client: JsonRpcClient::new(http_url),
+            mode,
+            include_failed,
+        }
+    }
+
+    fn subscription_requests(&self) -> Vec<Value> {
+        match self.mode {
+            RpcSubscriptionMode::Block => vec![json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "blockSubscribe",
+                "params": [
+                    "all",
+                    {
+                        "commitment": "confirmed",
+                        "encoding": "jsonParsed",
+                        "transactionDetails": "full",
+                        "showRewards": true,
+                        "maxSupportedTransactionVersion": 0,
+                    }
+                ]
+            })],
+            RpcSubscriptionMode::Logs => ProgramId::ALL.iter().enumerate().map(|(i, program)| json!({
+                "jsonrpc": "2.0",
+                "id": i + 1,
+                "method": "logsSubscribe",
+                "params": [
+                    { "mentions": [program.address()] },
+                    { "commitment": "confirmed" }
+                ]
+            })).collect(),
+        }
+    }
+
+    async fn handle_block_notification(
+        &self,
+        notification: RpcNotification<BlockNotificationValue>,
+        sender: &UnboundedSender<Result<TransactionNotificationResult, SourceError>>
+    ) {
+        let slot = notification.params.result.value.slot;
+        let transactions = notification.params.result.value.block
+            .and_then(|block| block.transactions)
+            .unwrap_or_default();
+
+        for transaction in transactions {
+            if (!self.include_failed && is_failed(&transaction)) || is_vote(&transaction) {
+                continue;
+            }
+
+            if let Some(signature) = first_signature(&transaction) {
+                let _ = sender.send(Ok(TransactionNotificationResult {
+                    transaction,
+                    signature,
+                    slot,
+                    frame: None,
+                }));
+            }
+        }
+    }
+
+    /// Queues the transaction to be fetched by `forward_fetched`.
+    async fn handle_logs_notification(
+        &self,
+        notification: RpcNotification<LogsNotificationValue>,
+        seen: &mut SignatureCache,
+        fetches: &Sender<(String, u64)>
+    ) {
+        let value = notification.params.result.value;
+        if !self.include_failed && value.err.is_some() {
+            return;
+        }
+
+        // A transaction touching several tracked programs is notified once per subscription.
+        if !seen.insert(&value.signature) {
+            return;
+        }
+
+        let _ = fetches.send((value.signature, notification.params.result.context.slot)).await;
+    }
+
+    async fn process_messages<S>(
+        &self,
+        mut read: S,
+        sender: UnboundedSender<Result<TransactionNotificationResult, SourceError>>
+    ) where
+        S: futures::Stream<Item = Result<WsMessage, tokio_tungstenite::tungstenite::Error>> + Unpin
+    {
+        let mut seen = SignatureCache::new(10_000);
+        let (fetches, fetch_receiver) = mpsc::channel(MAX_CONCURRENT_FETCHES);
+        tokio::spawn(forward_fetched(self.rpc_client.clone(), fetch_receiver, sender.clone()));
+        let request_count = self.subscription_requests().len() as u64;
+
+        while let Some(message) = read.next().await {
+            let text = match message {
+                Ok(WsMessage::Text(text)) => text,
+                Ok(WsMessage::Close(frame)) => {
+                    warn!("WebSocket connection closed: {:?}", frame);
+                    break;
+                }
+                Ok(_) => continue,
+                Err(err) => {
+                    warn!("Error processing messages: {}", err);
+                    let _ = sender.send(Err(err.into()));
+                    break;
+                }
+            };
+
+            if let Ok(notification) = serde_json::from_str::<RpcNotification<BlockNotificationValue>>(&text) {
+                if notification.method == "blockNotification" {
+                    self.handle_block_notification(notification, &sender).await;
+                }
+            } else if let Ok(notification) = serde_json::from_str::<RpcNotification<LogsNotificationValue>>(&text) {
+                if notification.method == "logsNotification" {
+                    self.handle_logs_notification(notification, &mut seen, &fetches).await;
+                }
+            } else if let Ok(value) = serde_json::from_str::<Value>(&text) {
+                if let Some(error) = value.get("error") {
+                    warn!("Subscription error: {}", error);
+                    let _ = sender.send(Err(format!("Subscription error: {}", error).into()));
+                    break;
+                }
+
+                // Subscription requests are numbered from 1, see `subscription_requests`.
+                let is_subscribe_response = value.get("id")
+                    .and_then(Value::as_u64)
+                    .is_some_and(|id| (1..=request_count).contains(&id))
+                    && value.get("result").is_some();
+
+                if is_subscribe_response {
+                    info!("Subscription successful: {}", text);
+                } else {
+                    warn!("Unknown message: {}", text);
+                }
+            } else {
+                warn!("Unknown message: {}", text);
+            }
+
+            if sender.is_closed() {
+                break;
+            }
+        }
+    }
+}
+
+impl TransactionSource for RpcSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn commitment(&self) -> CommitmentStatus {
+        CommitmentStatus::Confirmed
+    }
+
+    fn subscribe(&self) -> BoxFuture<'_, Result<NotificationStream, SourceError>> {
+        Box::pin(async move {
+            info!("Connecting to: {}", self.ws_url);
+            let (ws, _response) = connect_async(&self.ws_url).await?;
+            info!("WebSocket connection established!");
+
+            let (mut write, read) = ws.split();
+            for request in self.subscription_requests() {
+                write.send(WsMessage::Text(request.to_string())).await?;
+            }
+            info!("Subscription to {:?} notifications successfully sent.", self.mode);
+
+            let (sender, receiver) = mpsc::unbounded_channel();
+            let this = self.clone();
+            tokio::spawn(async move {
+                let heartbeat = tokio::spawn(async move {
+                    loop {
+                        tokio::time::sleep(Duration::from_secs(30)).await;
+                        if write.send(WsMessage::Ping(vec![])).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+
+                this.process_messages(read, sender).await;
+                heartbeat.abort();
+            });
+
+            Ok(Box::pin(UnboundedReceiverStream::new(receiver)) as NotificationStream)
+        })
+    }
+}
+
+fn is_failed(transaction: &EncodedTransactionWithStatusMeta) -> bool {
+    transaction.meta.as_ref().map(|meta| meta.err.is_some()).unwrap_or(false)
+}
+
+fn is_vote(transaction: &EncodedTransactionWithStatusMeta) -> bool {
+    match &transaction.transaction {
+        EncodedTransaction::Json(ui_transaction) => match &ui_transaction.message {
+            UiMessage::Parsed(message) => message.account_keys.iter().any(|account| account.pubkey == VOTE_PROGRAM_ID),
+            UiMessage::Raw(message) => message.account_keys.iter().any(|account| account == VOTE_PROGRAM_ID),
+        },
+        _ => false,
+    }
+}
+
+fn first_signature(transaction: &EncodedTransactionWithStatusMeta) -> Option<String> {
+    match &transaction.transaction {
+        EncodedTransaction::Json(ui_transaction) => ui_transaction.signatures.first().cloned(),
+        EncodedTransaction::Accounts(accounts) => accounts.signatures.first().cloned(),
+        other => other.decode().and_then(|tx| tx.signatures.first().map(|signature| signature.to_string())),
+    }
+}
+
+/// Fetches a confirmed transaction in the `jsonParsed` encoding the handlers expect, retrying
+/// with a short delay while the node does not have it available yet.
+/// Fetches the queued transactions concurrently, so that the retries of a transaction not yet
+/// retrievable do not hold back the fetches following it, but forwards them in the order they
+/// were notified in. The processor relies on transactions arriving in order.
+async fn forward_fetched(
+    rpc_client: JsonRpcClient,
+    fetches: Receiver<(String, u64)>,
+    sender: UnboundedSender<Result<TransactionNotificationResult, SourceError>>
+) {
+    let mut fetched = ReceiverStream::new(fetches)
+        .map(|(signature, slot)| {
+            let rpc_client = rpc_client.clone();
+            async move {
+                let transaction = get_transaction(&rpc_client, &signature).await;
+                (signature, slot, transaction)
+            }
+        })
+        .buffered(MAX_CONCURRENT_FETCHES);
+
+    while let Some((signature, slot, transaction)) = fetched.next().await {
+        match transaction {
+            Ok(Some(confirmed)) => {
+                let notification = TransactionNotificationResult {
+                    transaction: confirmed.transaction,
+                    signature,
+                    slot: confirmed.slot,
+                    frame: None,
+                };
+                if sender.send(Ok(notification)).is_err() {
+                    return;
+                }
+            }
+            Ok(None) => warn!("Transaction {} from slot {} could not be retrieved, skipping.", signature, slot),
+            Err(err) => warn!("Failed to retrieve transaction {}: {}", signature, err),
+        }
+    }
+}
+
+pub async fn get_transaction(
+    rpc_client: &JsonRpcClient,
+    signature: &str
+) -> Result<Option<EncodedConfirmedTransactionWithStatusMeta>, SourceError> {
+    for attempt in 0..GET_TRANSACTION_ATTEMPTS {
+        let transaction: Option<EncodedConfirmedTransactionWithStatusMeta> = rpc_client.call(
+            "getTransaction",
+            json!([
+                signature,
+                {
+                    "commitment": "confirmed",
+                    "encoding": "jsonParsed",
+                    "maxSupportedTransactionVersion": 0,
+                }
+            ])
+        ).await?;
+
+        if transaction.is_some() {
+            return Ok(transaction);
+        }
+
+        tokio::time::sleep(Duration::from_millis(200 * (attempt as u64 + 1))).await;
+    }
+
+    Ok(None)
+}
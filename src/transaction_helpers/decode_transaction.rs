@@ -0,0 +1,584 @@
+use solana_transaction_status::{
+    option_serializer::OptionSerializer,
+    parse_accounts::{parse_legacy_message_accounts, parse_v0_message_accounts, ParsedAccount, ParsedAccountSource},
+    parse_ui_instruction,
+    EncodedTransaction,
+    EncodedTransactionWithStatusMeta,
+    UiAddressTableLookup,
+    UiCompiledInstruction,
+    UiInnerInstructions,
+    UiInstruction,
+    UiMessage,
+    UiParsedMessage,
+    UiRawMessage
+};
+use solana_sdk::{
+    hash::Hash,
+    instruction::CompiledInstruction,
+    message::{
+        legacy,
+        v0::{self, LoadedAddresses, LoadedMessage, MessageAddressTableLookup},
+        AccountKeys,
+        VersionedMessage
+    },
+    pubkey::Pubkey,
+    reserved_account_keys::ReservedAccountKeys
+};
+use std::str::FromStr;
+
+use crate::helpers::address_lookup_tables::AddressLookupTableCache;
+
+/// The instruction and account views the handlers work with, regardless of the encoding the
+/// transaction was delivered in.
+pub struct DecodedTransaction {
+    /// Static account keys followed by the addresses loaded through lookup tables, so that
+    /// every account referenced by an instruction can be found in it.
+    pub message: UiParsedMessage,
+    /// Inner instructions grouped by the index of the top-level instruction invoking them.
+    pub inner_instructions: Vec<UiInnerInstructions>,
+}
+
+/// Reconstructs the `jsonParsed` view of a transaction. `jsonParsed` payloads are used as-is,
+/// raw JSON messages and base58/base64 encoded transactions are decoded and parsed locally, so
+/// that requesting a cheaper encoding from the provider does not change what the handlers see.
+///
+/// Addresses loaded by v0 transactions are taken from `meta.loaded_addresses`. Providers that
+/// omit them require `lookup_tables` to resolve the lookups against the chain.
+pub async fn decode_transaction(
+    transaction: &EncodedTransactionWithStatusMeta,
+    lookup_tables: Option<&AddressLookupTableCache>
+) -> Result<DecodedTransaction, String> {
+    let meta_loaded_addresses = match transaction.meta.as_ref().map(|meta| &meta.loaded_addresses) {
+        Some(OptionSerializer::Some(addresses)) => Some(LoadedAddresses {
+            writable: parse_pubkeys(&addresses.writable)?,
+            readonly: parse_pubkeys(&addresses.readonly)?,
+        }),
+        _ => None,
+    };
+
+    let message = match &transaction.transaction {
+        EncodedTransaction::Json(ui_transaction) => match &ui_transaction.message {
+            UiMessage::Parsed(message) => {
+                let mut message = message.clone();
+                if let Some(loaded_addresses) = &meta_loaded_addresses {
+                    merge_loaded_addresses(&mut message.account_keys, loaded_addresses);
+                }
+
+                return Ok(DecodedTransaction {
+                    message,
+                    inner_instructions: inner_instructions(transaction, None),
+                });
+            }
+            UiMessage::Raw(message) => raw_to_versioned_message(message)?,
+        },
+        encoded => encoded
+            .decode()
+            .ok_or("Failed to decode binary transaction")?
+            .message,
+    };
+
+    let loaded_addresses = match (&message, meta_loaded_addresses) {
+        (VersionedMessage::V0(message), loaded_addresses) if !message.address_table_lookups.is_empty() => {
+            let expected = message.address_table_lookups
+                .iter()
+                .map(|lookup| lookup.writable_indexes.len() + lookup.readonly_indexes.len())
+                .sum::<usize>();
+
+            match (loaded_addresses, lookup_tables) {
+                (Some(loaded_addresses), _) if loaded_addresses.len() == expected => loaded_addresses,
+                (_, Some(lookup_tables)) => lookup_tables
+                    .resolve(&message.address_table_lookups)
+                    .await
+                    .map_err(|e| format!("Failed to resolve address lookup tables: {}", e))?,
+                _ => return Err("Transaction uses address lookup tables but no loaded addresses are available".to_string()),
+            }
+        }
+        _ => LoadedAddresses::default(),
+    };
+
+    let reserved_account_keys = ReservedAccountKeys::new_all_activated();
+    let static_keys = message.static_account_keys();
+    let account_keys = AccountKeys::new(static_keys, Some(&loaded_addresses));
+
+    // `AccountKeys` panics on out of range indexes, which a malformed payload must not cause.
+    if !message.instructions().iter().all(|instruction| references_known_accounts(instruction, &account_keys)) {
+        return Err("Instruction references an account outside of the transaction".to_string());
+    }
+
+    let message = match &message {
+        VersionedMessage::Legacy(message) => UiParsedMessage {
+            account_keys: parse_legacy_message_accounts(message),
+            recent_blockhash: message.recent_blockhash.to_string(),
+            instructions: message.instructions
+                .iter()
+                .map(|instruction| parse_ui_instruction(instruction, &account_keys, None))
+                .collect(),
+            address_table_lookups: None,
+        },
+        VersionedMessage::V0(message) => {
+            let loaded_message = LoadedMessage::new_borrowed(
+                message,
+                &loaded_addresses,
+                &reserved_account_keys.active
+            );
+
+            UiParsedMessage {
+                account_keys: parse_v0_message_accounts(&loaded_message),
+                recent_blockhash: message.recent_blockhash.to_string(),
+                instructions: message.instructions
+                    .iter()
+                    .map(|instruction| parse_ui_instruction(instruction, &account_keys, None))
+                    .collect(),
+                address_table_lookups: Some(message.address_table_lookups.iter().map(Into::into).collect()),
+            }
+        }
+    };
+
+    Ok(DecodedTransaction {
+        message,
+        inner_instructions: inner_instructions(transaction, Some(&account_keys)),
+    })
+}
+
+/// Appends the loaded addresses a provider left out of a `jsonParsed` account list, keeping the
+/// same order as the runtime so indexes into the balance arrays of the meta stay valid.
+fn merge_loaded_addresses(account_keys: &mut Vec<ParsedAccount>, loaded_addresses: &LoadedAddresses) {
+    let loaded = loaded_addresses.writable
+        .iter()
+        .map(|address| (address, true))
+        .chain(loaded_addresses.readonly.iter().map(|address| (address, false)));
+
+    for (address, writable) in loaded {
+        let pubkey = address.to_string();
+        if account_keys.iter().any(|account| account.pubkey == pubkey) {
+            continue;
+        }
+
+        account_keys.push(ParsedAccount {
+            pubkey,
+            writable,
+            signer: false,
+            source: Some(ParsedAccountSource::LookupTable),
+        });
+    }
+}
+
+/// Copies the inner instructions from the transaction meta. Compiled inner instructions are
+/// parsed when the account keys needed to resolve them are known.
+fn inner_instructions(transaction: &EncodedTransactionWithStatusMeta, account_keys: Option<&AccountKeys>) -> Vec<UiInnerInstructions> {
+    let inner_instructions = match transaction.meta.as_ref().map(|meta| &meta.inner_instructions) {
+        Some(OptionSerializer::Some(inner_instructions)) => inner_instructions,
+        _ => return Vec::new(),
+    };
+
+    inner_instructions
+        .iter()
+        .map(|ui_inner| UiInnerInstructions {
+            index: ui_inner.index,
+            instructions: ui_inner.instructions
+                .iter()
+                .map(|instruction| match (instruction, account_keys) {
+                    (UiInstruction::Compiled(compiled), Some(account_keys)) => match to_compiled_instruction(compiled) {
+                        Ok(parsed) if references_known_accounts(&parsed, account_keys) => {
+                            parse_ui_instruction(&parsed, account_keys, compiled.stack_height)
+                        }
+                        _ => instruction.clone(),
+                    },
+                    _ => instruction.clone(),
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+fn references_known_accounts(instruction: &CompiledInstruction, account_keys: &AccountKeys) -> bool {
+    (instruction.program_id_index as usize) < account_keys.len()
+        && instruction.accounts.iter().all(|index| (*index as usize) < account_keys.len())
+}
+
+fn raw_to_versioned_message(message: &UiRawMessage) -> Result<VersionedMessage, String> {
+    let account_keys = parse_pubkeys(&message.account_keys)?;
+    let recent_blockhash = Hash::from_str(&message.recent_blockhash)
+        .map_err(|e| format!("Invalid recent blockhash {}: {}", message.recent_blockhash, e))?;
+    let instructions = message.instructions
+        .iter()
+        .map(to_compiled_instruction)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match &message.address_table_lookups {
+        Some(lookups) => Ok(VersionedMessage::V0(v0::Message {
+            header: message.header,
+            account_keys,
+            recent_blockhash,
+            instructions,
+            address_table_lookups: lookups
+                .iter()
+                .map(to_address_table_lookup)
+                .collect::<Result<Vec<_>, _>>()?,
+        })),
+        None => Ok(VersionedMessage::Legacy(legacy::Message {
+            header: message.header,
+            account_keys,
+            recent_blockhash,
+            instructions,
+        })),
+    }
+}
+
+fn to_compiled_instruction(instruction: &UiCompiledInstruction) -> Result<CompiledInstruction, String> {
+    let data = bs58::decode(&instruction.data)
+        .into_vec()
+        .map_err(|e| format!("Invalid instruction data: {}", e))?;
+
+    Ok(CompiledInstruction {
+        program_id_index: instruction.program_id_index,
+        accounts: instruction.accounts.clone(),
+        data,
+    })
+}
+
+fn to_address_table_lookup(lookup: &UiAddressTableLookup) -> Result<MessageAddressTableLookup, String> {
+    Ok(MessageAddressTableLookup {
+        account_key: Pubkey::from_str(&lookup.account_key)
+            .map_err(|e| format!("Invalid lookup table address {}: {}", lookup.account_key, e))?,
+        writable_indexes: lookup.writable_indexes.clone(),
+        readonly_indexes: lookup.readonly_indexes.clone(),
+    })
+}
+
+fn parse_pubkeys(addresses: &[String]) -> Result<Vec<Pubkey>, String> {
+    addresses
+        .iter()
+        .map(|address| Pubkey::from_str(address).map_err(|e| format!("Invalid address {}: {}", address, e)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, routing::post, Json, Router};
+    use base64::{prelude::BASE64_STANDARD, Engine};
+    use serde_json::{json, Value};
+    use solana_sdk::{
+        address_lookup_table::state::{AddressLookupTable, LookupTableMeta},
+        message::MessageHeader,
+        signature::{Keypair, Signature},
+        signer::Signer,
+        system_instruction,
+        system_transaction,
+        transaction::VersionedTransaction
+    };
+    use solana_transaction_status::{
+        parse_instruction::ParsedInstruction,
+        TransactionBinaryEncoding,
+        UiParsedInstruction,
+        UiLoadedAddresses,
+        UiTransaction,
+        UiTransactionStatusMeta
+    };
+    use std::{borrow::Cow, sync::{atomic::{AtomicUsize, Ordering}, Arc}};
+    use tokio::net::TcpListener;
+
+    const SYSTEM_PROGRAM_ID: &str = "11111111111111111111111111111111";
+
+    fn meta() -> UiTransactionStatusMeta {
+        serde_json::from_value(json!({
+            "err": null,
+            "status": { "Ok": null },
+            "fee": 5000,
+            "preBalances": [],
+            "postBalances": [],
+        })).unwrap()
+    }
+
+    fn with_meta(transaction: EncodedTransaction, meta: UiTransactionStatusMeta) -> EncodedTransactionWithStatusMeta {
+        EncodedTransactionWithStatusMeta { transaction, meta: Some(meta), version: None }
+    }
+
+    fn base58(transaction: &VersionedTransaction) -> EncodedTransaction {
+        EncodedTransaction::LegacyBinary(bs58::encode(bincode::serialize(transaction).unwrap()).into_string())
+    }
+
+    fn base64(transaction: &VersionedTransaction) -> EncodedTransaction {
+        EncodedTransaction::Binary(
+            BASE64_STANDARD.encode(bincode::serialize(transaction).unwrap()),
+            TransactionBinaryEncoding::Base64
+        )
+    }
+
+    fn parsed(instruction: &UiInstruction) -> &ParsedInstruction {
+        match instruction {
+            UiInstruction::Parsed(UiParsedInstruction::Parsed(parsed)) => parsed,
+            other => panic!("Expected a parsed instruction, got {:?}", other),
+        }
+    }
+
+    fn pubkeys(message: &UiParsedMessage) -> Vec<String> {
+        message.account_keys.iter().map(|account| account.pubkey.clone()).collect()
+    }
+
+    #[tokio::test]
+    async fn decodes_base58_and_base64_legacy_transactions() {
+        let payer = Keypair::new();
+        let recipient = Pubkey::new_unique();
+        let transfer = VersionedTransaction::from(system_transaction::transfer(&payer, &recipient, 1000, Hash::new_unique()));
+
+        for encoded in [base58(&transfer), base64(&transfer)] {
+            let decoded = decode_transaction(&with_meta(encoded, meta()), None).await.unwrap();
+            let message = decoded.message;
+
+            assert_eq!(pubkeys(&message), vec![payer.pubkey().to_string(), recipient.to_string(), SYSTEM_PROGRAM_ID.to_string()]);
+            assert!(message.account_keys[0].signer && message.account_keys[0].writable);
+            assert!(!message.account_keys[1].signer && message.account_keys[1].writable);
+            assert!(!message.account_keys[2].writable);
+            assert_eq!(message.recent_blockhash, transfer.message.recent_blockhash().to_string());
+            assert!(message.address_table_lookups.is_none());
+
+            let instruction = parsed(&message.instructions[0]);
+            assert_eq!(instruction.program, "system");
+            assert_eq!(instruction.parsed["type"], "transfer");
+            assert_eq!(instruction.parsed["info"]["destination"], recipient.to_string());
+            assert_eq!(instruction.parsed["info"]["lamports"], 1000);
+        }
+    }
+
+    #[tokio::test]
+    async fn decodes_raw_json_messages() {
+        let payer = Keypair::new();
+        let recipient = Pubkey::new_unique();
+        let transfer = system_transaction::transfer(&payer, &recipient, 1000, Hash::new_unique());
+        let message = &transfer.message;
+
+        let raw = EncodedTransaction::Json(UiTransaction {
+            signatures: transfer.signatures.iter().map(ToString::to_string).collect(),
+            message: UiMessage::Raw(UiRawMessage {
+                header: message.header,
+                account_keys: message.account_keys.iter().map(ToString::to_string).collect(),
+                recent_blockhash: message.recent_blockhash.to_string(),
+                instructions: message.instructions.iter().map(|instruction| UiCompiledInstruction::from(instruction, None)).collect(),
+                address_table_lookups: None,
+            }),
+        });
+
+        let decoded = decode_transaction(&with_meta(raw, meta()), None).await.unwrap();
+        let binary = decode_transaction(&with_meta(base64(&VersionedTransaction::from(transfer)), meta()), None).await.unwrap();
+        assert_eq!(decoded.message, binary.message);
+        assert_eq!(parsed(&decoded.message.instructions[0]).parsed["info"]["destination"], recipient.to_string());
+    }
+
+    #[tokio::test]
+    async fn parses_compiled_inner_instructions() {
+        let payer = Keypair::new();
+        let recipient = Pubkey::new_unique();
+        let transfer = VersionedTransaction::from(system_transaction::transfer(&payer, &recipient, 1000, Hash::new_unique()));
+
+        let mut meta = meta();
+        meta.inner_instructions = OptionSerializer::Some(vec![UiInnerInstructions {
+            index: 0,
+            instructions: vec![
+                UiInstruction::Compiled(UiCompiledInstruction {
+                    program_id_index: 2,
+                    accounts: vec![0, 1],
+                    data: bs58::encode(bincode::serialize(&system_instruction::SystemInstruction::Transfer { lamports: 5 }).unwrap()).into_string(),
+                    stack_height: Some(2),
+                }),
+                // An instruction referencing an unknown account is kept as-is rather than panicking.
+                UiInstruction::Compiled(UiCompiledInstruction {
+                    program_id_index: 2,
+                    accounts: vec![0, 9],
+                    data: String::new(),
+                    stack_height: Some(2),
+                }),
+            ],
+        }]);
+
+        let decoded = decode_transaction(&with_meta(base64(&transfer), meta), None).await.unwrap();
+        let inner = &decoded.inner_instructions[0];
+        assert_eq!(inner.index, 0);
+        assert_eq!(parsed(&inner.instructions[0]).parsed["info"]["lamports"], 5);
+        assert!(matches!(inner.instructions[1], UiInstruction::Compiled(_)));
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_transactions() {
+        let garbage = EncodedTransaction::Binary(BASE64_STANDARD.encode([1, 2, 3]), TransactionBinaryEncoding::Base64);
+        assert!(decode_transaction(&with_meta(garbage, meta()), None).await.is_err());
+
+        let not_base58 = EncodedTransaction::LegacyBinary("0OIl".to_string());
+        assert!(decode_transaction(&with_meta(not_base58, meta()), None).await.is_err());
+
+        // An instruction pointing past the account keys must not make `AccountKeys` panic.
+        let payer = Keypair::new();
+        let mut instruction = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1);
+        instruction.accounts.truncate(1);
+        let mut message = legacy::Message::new(&[instruction], Some(&payer.pubkey()));
+        message.instructions[0].accounts.push(42);
+        let transaction = VersionedTransaction {
+            signatures: vec![Signature::default()],
+            message: VersionedMessage::Legacy(message),
+        };
+        assert!(decode_transaction(&with_meta(base64(&transaction), meta()), None).await.is_err());
+    }
+
+    /// A v0 transfer from the payer to the writable address loaded from `table`, which also
+    /// loads a readonly address.
+    struct LookupFixture {
+        payer: Keypair,
+        table: Pubkey,
+        addresses: Vec<Pubkey>,
+        transaction: VersionedTransaction,
+    }
+
+    impl LookupFixture {
+        fn new() -> Self {
+            let payer = Keypair::new();
+            let table = Pubkey::new_unique();
+            let addresses: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+
+            let message = v0::Message {
+                header: MessageHeader {
+                    num_required_signatures: 1,
+                    num_readonly_signed_accounts: 0,
+                    num_readonly_unsigned_accounts: 1,
+                },
+                account_keys: vec![payer.pubkey(), Pubkey::from_str(SYSTEM_PROGRAM_ID).unwrap()],
+                recent_blockhash: Hash::new_unique(),
+                instructions: vec![CompiledInstruction {
+                    program_id_index: 1,
+                    accounts: vec![0, 2],
+                    data: bincode::serialize(&system_instruction::SystemInstruction::Transfer { lamports: 1000 }).unwrap(),
+                }],
+                address_table_lookups: vec![MessageAddressTableLookup {
+                    account_key: table,
+                    writable_indexes: vec![2],
+                    readonly_indexes: vec![0],
+                }],
+            };
+
+            let transaction = VersionedTransaction {
+                signatures: vec![Signature::default()],
+                message: VersionedMessage::V0(message),
+            };
+
+            Self { payer, table, addresses, transaction }
+        }
+
+        fn loaded_addresses(&self) -> UiLoadedAddresses {
+            UiLoadedAddresses {
+                writable: vec![self.addresses[2].to_string()],
+                readonly: vec![self.addresses[0].to_string()],
+            }
+        }
+
+        fn assert_resolved(&self, message: &UiParsedMessage) {
+            assert_eq!(pubkeys(message), vec![
+                self.payer.pubkey().to_string(),
+                SYSTEM_PROGRAM_ID.to_string(),
+                self.addresses[2].to_string(),
+                self.addresses[0].to_string(),
+            ]);
+            assert!(message.account_keys[2].writable);
+            assert!(!message.account_keys[3].writable);
+            assert_eq!(message.account_keys[2].source, Some(ParsedAccountSource::LookupTable));
+            assert_eq!(parsed(&message.instructions[0]).parsed["info"]["destination"], self.addresses[2].to_string());
+        }
+    }
+
+    /// Serves `getMultipleAccounts` for a single lookup table, counting the requests.
+    async fn serve_lookup_table(table: Pubkey, addresses: Vec<Pubkey>) -> (String, Arc<AtomicUsize>) {
+        async fn handle_request(
+            State((table, data, requests)): State<(Pubkey, String, Arc<AtomicUsize>)>,
+            Json(request): Json<Value>
+        ) -> Json<Value> {
+            requests.fetch_add(1, Ordering::SeqCst);
+            let accounts: Vec<Value> = request["params"][0].as_array().unwrap().iter()
+                .map(|address| match address.as_str() == Some(table.to_string().as_str()) {
+                    true => json!({ "data": [data, "base64"] }),
+                    false => Value::Null,
+                })
+                .collect();
+
+            Json(json!({ "jsonrpc": "2.0", "result": { "value": accounts }, "id": request["id"] }))
+        }
+
+        let data = AddressLookupTable { meta: LookupTableMeta::default(), addresses: Cow::Owned(addresses) }
+            .serialize_for_tests()
+            .unwrap();
+        let requests = Arc::new(AtomicUsize::new(0));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let app = Router::new()
+            .route("/", post(handle_request))
+            .with_state((table, BASE64_STANDARD.encode(data), requests.clone()));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        (url, requests)
+    }
+
+    #[tokio::test]
+    async fn resolves_lookups_from_the_meta() {
+        let fixture = LookupFixture::new();
+        let mut meta = meta();
+        meta.loaded_addresses = OptionSerializer::Some(fixture.loaded_addresses());
+
+        for encoded in [base58(&fixture.transaction), base64(&fixture.transaction)] {
+            let decoded = decode_transaction(&with_meta(encoded, meta.clone()), None).await.unwrap();
+            fixture.assert_resolved(&decoded.message);
+            assert_eq!(decoded.message.address_table_lookups.unwrap()[0].account_key, fixture.table.to_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn resolves_lookups_against_the_chain_without_loaded_addresses() {
+        let fixture = LookupFixture::new();
+        let encoded = with_meta(base64(&fixture.transaction), meta());
+
+        // Without loaded addresses nor lookup tables the accounts cannot be known.
+        assert!(decode_transaction(&encoded, None).await.is_err());
+
+        let (url, requests) = serve_lookup_table(fixture.table, fixture.addresses.clone()).await;
+        let lookup_tables = AddressLookupTableCache::new(&url);
+        for _ in 0..2 {
+            let decoded = decode_transaction(&encoded, Some(&lookup_tables)).await.unwrap();
+            fixture.assert_resolved(&decoded.message);
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        // Incomplete loaded addresses are resolved against the chain as well.
+        let mut meta = meta();
+        meta.loaded_addresses = OptionSerializer::Some(UiLoadedAddresses {
+            writable: vec![fixture.addresses[2].to_string()],
+            readonly: vec![],
+        });
+        let decoded = decode_transaction(&with_meta(base58(&fixture.transaction), meta), Some(&lookup_tables)).await.unwrap();
+        fixture.assert_resolved(&decoded.message);
+    }
+
+    #[tokio::test]
+    async fn merges_loaded_addresses_into_json_parsed_messages() {
+        let fixture = LookupFixture::new();
+        let mut meta = meta();
+        meta.loaded_addresses = OptionSerializer::Some(fixture.loaded_addresses());
+
+        // A provider listing only the static keys of a `jsonParsed` message.
+        let resolved = decode_transaction(&with_meta(base64(&fixture.transaction), meta.clone()), None).await.unwrap().message;
+        let mut message = resolved.clone();
+        message.account_keys.truncate(2);
+
+        let json_parsed = EncodedTransaction::Json(UiTransaction {
+            signatures: vec![Signature::default().to_string()],
+            message: UiMessage::Parsed(message),
+        });
+        let decoded = decode_transaction(&with_meta(json_parsed, meta.clone()), None).await.unwrap();
+        assert_eq!(decoded.message, resolved);
+
+        // Keys already listed by the provider are not duplicated.
+        let json_parsed = EncodedTransaction::Json(UiTransaction {
+            signatures: vec![Signature::default().to_string()],
+            message: UiMessage::Parsed(resolved.clone()),
+        });
+        let decoded = decode_transaction(&with_meta(json_parsed, meta), None).await.unwrap();
+        assert_eq!(decoded.message, resolved);
+    }
+}
@@ -1,2 +1,3 @@
 pub mod find_token_balance_by_address;
-pub mod compile_balance_updates;
\ No newline at end of file
+pub mod compile_balance_updates;
+pub mod decode_transaction;
\ No newline at end of file